@@ -0,0 +1,227 @@
+//! Plays Players against each other, one round at a time, without trusting them to play
+//! legal moves or to finish.
+
+use crate::config::{ConfigError, GameConfig};
+use crate::engine::{Action, GameState, IllegalMoveReason, PlayerId, TransitionResult};
+use crate::players::player::Player;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArenaConfig {
+    pub game: GameConfig,
+    /// Accepted moves after which an unfinished round is stopped
+    pub max_moves: usize,
+    /// Illegal moves a player may make in a round. Each is rejected and the player asked
+    /// again, one more forfeits the round.
+    pub max_illegal_moves: usize,
+}
+
+impl ArenaConfig {
+    pub fn new(game: GameConfig) -> Self {
+        ArenaConfig {
+            game,
+            max_moves: 1000,
+            max_illegal_moves: 0,
+        }
+    }
+}
+
+/// How a round played by play_game ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GameEnd {
+    /// By the rules, the scores are those of GameComplete
+    Complete,
+    /// The player made more illegal moves than allowed. They score minus the hand size and
+    /// every other player 0.
+    Forfeit(PlayerId),
+    /// Stopped after max_moves, every player scores 0
+    MoveLimit,
+}
+
+/// What happened in a round played by play_game
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameRecord {
+    pub seed: u64,
+    pub first_player: PlayerId,
+    /// The accepted moves, as in PublicState::action_history
+    pub history: Vec<(PlayerId, Action, TransitionResult)>,
+    /// The rejected moves and why they were rejected
+    pub illegal_moves: Vec<(PlayerId, Action, IllegalMoveReason)>,
+    pub end: GameEnd,
+    /// Indexed by PlayerId
+    pub scores: Vec<i8>,
+}
+
+/// Deals a round of config.game from seed and plays it with a player per seat, calling the
+/// hooks of the Player trait except new_match. Each player only sees their own hand. Fails
+/// if the config is invalid or the number of players does not match it.
+pub fn play_game(
+    config: &ArenaConfig,
+    seed: u64,
+    players: &mut [Box<dyn Player>],
+) -> Result<GameRecord, ConfigError> {
+    let mut state = GameState::new(&config.game, seed)?;
+    let num_players = state.num_players();
+    if players.len() != num_players {
+        return Err(ConfigError::PlayerCount {
+            expected: num_players,
+            given: players.len(),
+        });
+    }
+    for (seat, player) in players.iter_mut().enumerate() {
+        player.new_round(seat, &state.public_state);
+    }
+
+    let mut illegal_moves = vec![];
+    let mut num_illegal_moves = vec![0; num_players];
+    let (end, scores) = loop {
+        if state.public_state.action_history.len() >= config.max_moves {
+            break (GameEnd::MoveLimit, vec![0; num_players]);
+        }
+        let actor = state.public_state.current_player;
+        let action = players[actor].choose_action(&state.public_state, &state.hidden_states[actor]);
+        let result = state.transition(&action);
+        if let TransitionResult::IllegalMove(reason) = result {
+            illegal_moves.push((actor, action, reason));
+            num_illegal_moves[actor] += 1;
+            if num_illegal_moves[actor] > config.max_illegal_moves {
+                let mut scores = vec![0; num_players];
+                scores[actor] = -(config.game.hand_size as i8);
+                break (GameEnd::Forfeit(actor), scores);
+            }
+            continue;
+        }
+        for player in players.iter_mut() {
+            player.observe(&action, &result, actor);
+        }
+        if let TransitionResult::GameComplete(scores) = result {
+            break (GameEnd::Complete, scores);
+        }
+    };
+
+    for player in players.iter_mut() {
+        player.end_round(&scores);
+    }
+    Ok(GameRecord {
+        seed,
+        first_player: state.public_state.first_player,
+        history: state.public_state.action_history,
+        illegal_moves,
+        end,
+        scores,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{PlayerHiddenState, PublicState};
+    use crate::players::by_name;
+    use crate::players::player::{Stateless, StatelessPlayer};
+    use crate::players::trivial_player::TrivialPlayer;
+
+    /// Tries a move out of the hand before each move of TrivialPlayer
+    struct CarelessPlayer {
+        tried: bool,
+    }
+
+    impl Player for CarelessPlayer {
+        fn choose_action(
+            &mut self,
+            public_state: &PublicState,
+            hidden_state: &PlayerHiddenState,
+        ) -> Action {
+            self.tried = !self.tried;
+            if self.tried {
+                Action::PlayCards(u8::MAX - 1, u8::MAX)
+            } else {
+                TrivialPlayer {}.choose_action(public_state, hidden_state)
+            }
+        }
+    }
+
+    fn careless() -> Box<dyn Player> {
+        Box::new(CarelessPlayer { tried: false })
+    }
+
+    #[test]
+    fn test_plays_to_the_end() {
+        let config = ArenaConfig::new(GameConfig::new(3, 10, 3));
+        let mut players: Vec<Box<dyn Player>> = ["greedy", "random", "scout_averse"]
+            .iter()
+            .map(|name| by_name(name, &config.game, 1).unwrap())
+            .collect();
+        let record = play_game(&config, 2, &mut players).unwrap();
+        assert_eq!(GameEnd::Complete, record.end);
+        assert!(record.illegal_moves.is_empty());
+
+        // The record replays to the same scores
+        let mut state = GameState::new(&config.game, record.seed).unwrap();
+        for (actor, action, result) in &record.history {
+            assert_eq!(*actor, state.public_state.current_player);
+            assert_eq!(*result, state.transition(action));
+        }
+        assert_eq!(
+            TransitionResult::GameComplete(record.scores),
+            record.history.last().unwrap().2
+        );
+
+        let config = ArenaConfig::new(GameConfig::new(1, 10, 3));
+        assert_eq!(
+            Err(ConfigError::NumPlayers(1)),
+            play_game(&config, 2, &mut players)
+        );
+        let config = ArenaConfig::new(GameConfig::new(2, 10, 3));
+        assert_eq!(
+            Err(ConfigError::PlayerCount {
+                expected: 2,
+                given: 3
+            }),
+            play_game(&config, 2, &mut players)
+        );
+    }
+
+    #[test]
+    fn test_illegal_moves() {
+        let mut config = ArenaConfig::new(GameConfig::new(2, 10, 3));
+        let mut players = vec![Box::new(Stateless(TrivialPlayer {})) as _, careless()];
+        let record = play_game(&config, 3, &mut players).unwrap();
+        assert_eq!(GameEnd::Forfeit(1), record.end);
+        assert_eq!(vec![0, -(config.game.hand_size as i8)], record.scores);
+        assert_eq!(
+            vec![(
+                1,
+                Action::PlayCards(u8::MAX - 1, u8::MAX),
+                IllegalMoveReason::MustChooseOrientation
+            )],
+            record.illegal_moves
+        );
+        assert_eq!(1, record.history.len());
+
+        // Rejected moves leave the round as it was
+        config.max_illegal_moves = usize::MAX;
+        let mut players = vec![careless(), careless()];
+        let record = play_game(&config, 3, &mut players).unwrap();
+        let mut players: Vec<Box<dyn Player>> = vec![
+            Box::new(Stateless(TrivialPlayer {})),
+            Box::new(Stateless(TrivialPlayer {})),
+        ];
+        let trivial_record = play_game(&config, 3, &mut players).unwrap();
+        assert_eq!(GameEnd::Complete, record.end);
+        assert_eq!(trivial_record.history, record.history);
+        assert_eq!(record.history.len(), record.illegal_moves.len());
+    }
+
+    #[test]
+    fn test_move_limit() {
+        let mut config = ArenaConfig::new(GameConfig::new(2, 10, 3));
+        config.max_moves = 5;
+        let mut players = vec![
+            by_name("random", &config.game, 1).unwrap(),
+            by_name("random", &config.game, 2).unwrap(),
+        ];
+        let record = play_game(&config, 3, &mut players).unwrap();
+        assert_eq!(GameEnd::MoveLimit, record.end);
+        assert_eq!(5, record.history.len());
+        assert_eq!(vec![0, 0], record.scores);
+    }
+}
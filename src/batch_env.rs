@@ -0,0 +1,231 @@
+use rand::{RngCore, SeedableRng};
+use rand_xoshiro::SplitMix64;
+
+use crate::config::{ConfigError, GameConfig};
+use crate::encoding::{ActionSpace, ObservationEncoder};
+use crate::engine::{Action, GameState, PlayerId, TransitionResult};
+
+/// Caller owned buffers a BatchEnv writes into, each holding a row per game back to back
+pub struct BatchBuffers<'a> {
+    /// ObservationEncoder::size() values per game, for the player to act
+    pub observations: &'a mut [f32],
+    /// ActionSpace::size() values per game, for the player to act
+    pub legal_masks: &'a mut [bool],
+    /// A value per player per game, the round scores when the game just completed, else 0
+    pub rewards: &'a mut [f32],
+    /// Whether the game just completed, its row then describes the next game already dealt
+    pub dones: &'a mut [bool],
+    pub current_players: &'a mut [PlayerId],
+}
+
+/// Independent games of the same config stepped together for self-play. Finished games are
+/// dealt again straight away. Outputs go to caller provided buffers, nothing is allocated for
+/// them per step.
+pub struct BatchEnv {
+    action_space: ActionSpace,
+    encoder: ObservationEncoder,
+    config: GameConfig,
+    games: Vec<GameState>,
+    /// Per game, the seeds of its successive deals
+    rngs: Vec<SplitMix64>,
+}
+
+impl BatchEnv {
+    /// A game per seed. The deals of a game use the outputs of SplitMix64 seeded with its seed,
+    /// in order, as for Match rounds.
+    pub fn new(config: GameConfig, seeds: &[u64], history_len: usize) -> Result<Self, ConfigError> {
+        config.validate()?;
+        let mut rngs: Vec<SplitMix64> = seeds
+            .iter()
+            .map(|&seed| SplitMix64::seed_from_u64(seed))
+            .collect();
+        let games = rngs
+            .iter_mut()
+            .map(|rng| GameState::new(&config, rng.next_u64()).unwrap())
+            .collect();
+        Ok(BatchEnv {
+            action_space: ActionSpace::new(&config),
+            encoder: ObservationEncoder::new(&config, history_len),
+            config,
+            games,
+            rngs,
+        })
+    }
+
+    pub fn num_envs(&self) -> usize {
+        self.games.len()
+    }
+
+    pub fn num_players(&self) -> usize {
+        self.config.num_players
+    }
+
+    pub fn action_space(&self) -> &ActionSpace {
+        &self.action_space
+    }
+
+    pub fn encoder(&self) -> &ObservationEncoder {
+        &self.encoder
+    }
+
+    pub fn games(&self) -> &[GameState] {
+        &self.games
+    }
+
+    fn check_buffers(&self, buffers: &BatchBuffers) {
+        let num_envs = self.num_envs();
+        assert_eq!(num_envs * self.encoder.size(), buffers.observations.len());
+        assert_eq!(
+            num_envs * self.action_space.size(),
+            buffers.legal_masks.len()
+        );
+        assert_eq!(num_envs * self.num_players(), buffers.rewards.len());
+        assert_eq!(num_envs, buffers.dones.len());
+        assert_eq!(num_envs, buffers.current_players.len());
+    }
+
+    fn write_state(&self, env: usize, buffers: &mut BatchBuffers) {
+        let state = &self.games[env];
+        let player = state.public_state.current_player;
+        let observation_size = self.encoder.size();
+        let mask_size = self.action_space.size();
+        self.encoder.encode_into(
+            &state.public_state,
+            &state.hidden_states[player],
+            player,
+            &mut buffers.observations[env * observation_size..(env + 1) * observation_size],
+        );
+        self.action_space.legal_action_mask_into(
+            &state.public_state,
+            &state.hidden_states[player],
+            &mut buffers.legal_masks[env * mask_size..(env + 1) * mask_size],
+        );
+        buffers.current_players[env] = player;
+    }
+
+    /// Writes the current state of every game, with no rewards and no game done
+    pub fn observe(&self, buffers: &mut BatchBuffers) {
+        self.check_buffers(buffers);
+        buffers.rewards.fill(0.0);
+        buffers.dones.fill(false);
+        for env in 0..self.num_envs() {
+            self.write_state(env, buffers);
+        }
+    }
+
+    /// Plays an action index in every game, actions[env] must be legal in game env
+    pub fn step(&mut self, actions: &[usize], buffers: &mut BatchBuffers) {
+        self.check_buffers(buffers);
+        assert_eq!(self.num_envs(), actions.len());
+        let num_players = self.num_players();
+        for (env, &action_idx) in actions.iter().enumerate() {
+            let action = Action::from_index(&self.action_space, action_idx)
+                .unwrap_or_else(|| panic!("No action with index {} in env {}", action_idx, env));
+            let rewards = &mut buffers.rewards[env * num_players..(env + 1) * num_players];
+            match self.games[env].transition(&action) {
+                TransitionResult::IllegalMove(reason) => {
+                    panic!("Illegal move ({:?}) in env {}: {:?}", reason, env, action);
+                }
+                TransitionResult::MoveAccepted => {
+                    rewards.fill(0.0);
+                    buffers.dones[env] = false;
+                }
+                TransitionResult::GameComplete(scores) => {
+                    for (reward, score) in rewards.iter_mut().zip(scores) {
+                        *reward = score as f32;
+                    }
+                    buffers.dones[env] = true;
+                    self.games[env] =
+                        GameState::new(&self.config, self.rngs[env].next_u64()).unwrap();
+                }
+            }
+            self.write_state(env, buffers);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::seq::IteratorRandom;
+
+    #[test]
+    fn test_step_matches_single_games() {
+        let config = GameConfig::new(3, 8, 1);
+        let seeds = [1, 2, 3, 4];
+        let mut env = BatchEnv::new(config.clone(), &seeds, 4).unwrap();
+        let num_envs = env.num_envs();
+        let mut observations = vec![0.0; num_envs * env.encoder().size()];
+        let mut legal_masks = vec![false; num_envs * env.action_space().size()];
+        let mut rewards = vec![0.0; num_envs * 3];
+        let mut dones = vec![false; num_envs];
+        let mut current_players = vec![0; num_envs];
+        let mut buffers = BatchBuffers {
+            observations: &mut observations,
+            legal_masks: &mut legal_masks,
+            rewards: &mut rewards,
+            dones: &mut dones,
+            current_players: &mut current_players,
+        };
+        env.observe(&mut buffers);
+
+        // The same games played one at a time
+        let mut rngs: Vec<SplitMix64> = seeds
+            .iter()
+            .map(|&seed| SplitMix64::seed_from_u64(seed))
+            .collect();
+        let mut games: Vec<GameState> = rngs
+            .iter_mut()
+            .map(|rng| GameState::new(&config, rng.next_u64()).unwrap())
+            .collect();
+
+        let mut rng = SplitMix64::seed_from_u64(9);
+        let mut num_done = 0;
+        let mask_size = env.action_space().size();
+        let observation_size = env.encoder().size();
+        for _ in 0..200 {
+            let actions: Vec<usize> = (0..num_envs)
+                .map(|idx| {
+                    let mask = &buffers.legal_masks[idx * mask_size..(idx + 1) * mask_size];
+                    (0..mask_size)
+                        .filter(|&a| mask[a])
+                        .choose(&mut rng)
+                        .unwrap()
+                })
+                .collect();
+            env.step(&actions, &mut buffers);
+
+            for idx in 0..num_envs {
+                let action = Action::from_index(env.action_space(), actions[idx]).unwrap();
+                let rewards = &buffers.rewards[idx * 3..(idx + 1) * 3];
+                match games[idx].transition(&action) {
+                    TransitionResult::GameComplete(scores) => {
+                        assert!(buffers.dones[idx]);
+                        let scores: Vec<f32> = scores.iter().map(|&s| s as f32).collect();
+                        assert_eq!(scores, rewards);
+                        games[idx] = GameState::new(&config, rngs[idx].next_u64()).unwrap();
+                        num_done += 1;
+                    }
+                    result => {
+                        assert_eq!(TransitionResult::MoveAccepted, result);
+                        assert!(!buffers.dones[idx]);
+                        assert_eq!([0.0; 3], rewards);
+                    }
+                }
+                assert_eq!(games[idx], env.games()[idx]);
+
+                let player = games[idx].public_state.current_player;
+                assert_eq!(player, buffers.current_players[idx]);
+                assert_eq!(
+                    env.encoder().encode(
+                        &games[idx].public_state,
+                        &games[idx].hidden_states[player],
+                        player
+                    ),
+                    buffers.observations[idx * observation_size..(idx + 1) * observation_size]
+                );
+            }
+        }
+        assert!(num_done > num_envs);
+    }
+}
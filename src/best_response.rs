@@ -0,0 +1,445 @@
+//! Best responses to a fixed Player for small configs. Every deal and every line the fixed
+//! player can play is enumerated, so the trees must be small enough to walk.
+
+use std::collections::HashMap;
+
+use crate::cfr::utilities;
+use crate::engine::{Action, GameState, PlayerId};
+use crate::players::player::Player;
+use crate::search::MoveIter;
+use crate::view::info_set_key;
+
+/// Result of best_response, expectations over the deals
+#[derive(Debug, Clone, PartialEq)]
+pub struct BestResponse {
+    /// Utility of the responder, their score minus the average score of the other players
+    pub utility: f64,
+    /// Score of the responder
+    pub score: f64,
+    /// Information sets of the responder
+    pub num_info_sets: usize,
+}
+
+enum NodeKind {
+    Terminal { utility: f64, score: f64 },
+    Responder { key: u64 },
+    Opponent,
+}
+
+struct Node {
+    kind: NodeKind,
+    /// Length of the action history
+    depth: usize,
+    /// Probability of the deal times the probability the fixed player plays to the node
+    reach: f64,
+    /// For the responder, a child per move in MoveIter order. For the fixed player the moves
+    /// they play, with their probability.
+    children: Vec<(usize, f64)>,
+}
+
+/// The fixed player's moves, found by asking it samples times
+struct FixedPolicy {
+    player: Box<dyn Player>,
+    samples: usize,
+    policies: HashMap<u64, Vec<(Action, f64)>>,
+}
+
+impl FixedPolicy {
+    /// deal is the state the round started from
+    fn policy(&mut self, deal: &GameState, state: &GameState) -> Vec<(Action, f64)> {
+        let public_state = &state.public_state;
+        let player = public_state.current_player;
+        let hidden_state = &state.hidden_states[player];
+        let key = info_set_key(player, public_state, hidden_state);
+        let (fixed, samples) = (&mut self.player, self.samples);
+        self.policies
+            .entry(key)
+            .or_insert_with(|| {
+                let mut counts: Vec<(Action, f64)> = vec![];
+                for _ in 0..samples {
+                    // The player is asked out of order, so it is shown the round up to here
+                    // before each question
+                    fixed.new_round(player, &deal.public_state);
+                    for (actor, action, result) in &public_state.action_history {
+                        fixed.observe(action, result, *actor);
+                    }
+                    let action = fixed.choose_action(public_state, hidden_state);
+                    match counts.iter_mut().find(|(counted, _)| *counted == action) {
+                        Some((_, count)) => *count += 1.0,
+                        None => counts.push((action, 1.0)),
+                    }
+                }
+                for (_, count) in &mut counts {
+                    *count /= samples as f64;
+                }
+                counts
+            })
+            .clone()
+    }
+}
+
+fn build_tree(
+    deal: &GameState,
+    state: &mut GameState,
+    responder: PlayerId,
+    reach: f64,
+    fixed: &mut FixedPolicy,
+    nodes: &mut Vec<Node>,
+) -> usize {
+    let depth = state.public_state.action_history.len();
+    if state.public_state.game_complete {
+        let scores = state.final_scores().unwrap_or_else(|| {
+            panic!("Game complete without final scores (seed:{:?})", state.seed)
+        });
+        return push_terminal(scores, responder, depth, reach, nodes);
+    }
+
+    let player = state.public_state.current_player;
+    let (kind, moves) = if player == responder {
+        let key = info_set_key(player, &state.public_state, &state.hidden_states[player]);
+        let moves = MoveIter::new(&state.public_state, state.current_hidden_state())
+            .map(|action| (action, 1.0))
+            .collect();
+        (NodeKind::Responder { key }, moves)
+    } else {
+        (NodeKind::Opponent, fixed.policy(deal, state))
+    };
+
+    let mut children = vec![];
+    for (action, probability) in moves {
+        let (result, undo) = state.transition_with_undo(&action);
+        let Some(undo) = undo else {
+            assert_ne!(
+                player, responder,
+                "Illegal move ({:?}) (seed:{:?}): {:?}",
+                result, state.seed, action
+            );
+            // The fixed player forfeits, as in arena::play_game
+            let mut scores = vec![0; state.num_players()];
+            scores[player] = -(deal.public_state.card_counts[player] as i8);
+            let child = push_terminal(&scores, responder, depth + 1, reach * probability, nodes);
+            children.push((child, probability));
+            continue;
+        };
+        let child = build_tree(deal, state, responder, reach * probability, fixed, nodes);
+        state.undo(undo);
+        children.push((child, probability));
+    }
+    nodes.push(Node {
+        kind,
+        depth,
+        reach,
+        children,
+    });
+    nodes.len() - 1
+}
+
+fn push_terminal(
+    scores: &[i8],
+    responder: PlayerId,
+    depth: usize,
+    reach: f64,
+    nodes: &mut Vec<Node>,
+) -> usize {
+    nodes.push(Node {
+        kind: NodeKind::Terminal {
+            utility: utilities(scores)[responder],
+            score: scores[responder] as f64,
+        },
+        depth,
+        reach,
+        children: vec![],
+    });
+    nodes.len() - 1
+}
+
+/// The best the responder can do against the player new_fixed builds playing every other
+/// seat, over deals that are equally likely. The player is asked samples times per
+/// information set to estimate its policy, 1 is exact for a player that always makes the same
+/// choice. Before each question it gets new_round for the seat to act and observe for every
+/// move so far. An illegal move of the player forfeits the round as in arena::play_game. The
+/// responder maximizes their utility, see cfr::utilities.
+pub fn best_response(
+    responder: PlayerId,
+    new_fixed: &dyn Fn() -> Box<dyn Player>,
+    deals: &[GameState],
+    samples: usize,
+) -> BestResponse {
+    assert!(samples > 0 && !deals.is_empty());
+    let mut player = new_fixed();
+    player.new_match(deals[0].num_players());
+    let mut fixed = FixedPolicy {
+        player,
+        samples,
+        policies: HashMap::new(),
+    };
+    let mut nodes = vec![];
+    let roots: Vec<usize> = deals
+        .iter()
+        .map(|deal| {
+            let mut state = deal.clone();
+            let reach = 1.0 / deals.len() as f64;
+            build_tree(deal, &mut state, responder, reach, &mut fixed, &mut nodes)
+        })
+        .collect();
+
+    // (utility, score) below each node. Children are one move deeper and every node of an
+    // information set is at the same depth, so going up a depth at a time every choice of the
+    // responder is made after the ones below it.
+    let mut values = vec![(0.0, 0.0); nodes.len()];
+    let max_depth = nodes.iter().map(|node| node.depth).max().unwrap();
+    let mut by_depth: Vec<Vec<usize>> = vec![vec![]; max_depth + 1];
+    for (idx, node) in nodes.iter().enumerate() {
+        by_depth[node.depth].push(idx);
+    }
+    let mut num_info_sets = 0;
+    for depth_nodes in by_depth.iter().rev() {
+        let mut info_sets: HashMap<u64, Vec<usize>> = HashMap::new();
+        for &idx in depth_nodes {
+            let node = &nodes[idx];
+            match node.kind {
+                NodeKind::Terminal { utility, score } => values[idx] = (utility, score),
+                NodeKind::Opponent => {
+                    values[idx] = node.children.iter().fold(
+                        (0.0, 0.0),
+                        |(utility, score), &(child, probability)| {
+                            (
+                                utility + probability * values[child].0,
+                                score + probability * values[child].1,
+                            )
+                        },
+                    );
+                }
+                NodeKind::Responder { key } => info_sets.entry(key).or_default().push(idx),
+            }
+        }
+
+        num_info_sets += info_sets.len();
+        for members in info_sets.values() {
+            let num_moves = nodes[members[0]].children.len();
+            let best_move = (0..num_moves)
+                .map(|move_idx| {
+                    let utility: f64 = members
+                        .iter()
+                        .map(|&idx| {
+                            let node = &nodes[idx];
+                            node.reach * values[node.children[move_idx].0].0
+                        })
+                        .sum();
+                    (move_idx, utility)
+                })
+                .max_by(|(_, a), (_, b)| a.total_cmp(b))
+                .unwrap()
+                .0;
+            for &idx in members {
+                values[idx] = values[nodes[idx].children[best_move].0];
+            }
+        }
+    }
+
+    let (utility, score) = roots.iter().fold((0.0, 0.0), |(utility, score), &root| {
+        (
+            utility + values[root].0 / deals.len() as f64,
+            score + values[root].1 / deals.len() as f64,
+        )
+    });
+    BestResponse {
+        utility,
+        score,
+        num_info_sets,
+    }
+}
+
+/// How much a best response gains against the player new_player builds, in points per
+/// round: the best response utility averaged over the seats, with the player in every other
+/// seat. The utilities of a round sum to zero, so this is 0 for an equilibrium of two players
+/// and positive otherwise.
+pub fn exploitability(
+    new_player: &dyn Fn() -> Box<dyn Player>,
+    deals: &[GameState],
+    samples: usize,
+) -> f64 {
+    let num_players = deals[0].num_players();
+    (0..num_players)
+        .map(|seat| best_response(seat, new_player, deals, samples).utility)
+        .sum::<f64>()
+        / num_players as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfr::tests::small_deals;
+    use crate::cfr::{CfrPlus, Strategy};
+    use crate::config::GameConfig;
+    use crate::engine::{PlayerHiddenState, PublicState, TransitionResult};
+    use crate::players::player::{Stateless, StatelessPlayer};
+    use crate::players::strategy_player::StrategyPlayer;
+    use crate::players::trivial_player::TrivialPlayer;
+
+    /// The best responder's utility when they can see every card
+    fn expectimax(state: &mut GameState, responder: PlayerId, fixed: &dyn StatelessPlayer) -> f64 {
+        if state.public_state.game_complete {
+            let scores = state.final_scores().unwrap();
+            return utilities(scores)[responder];
+        }
+        let actions: Vec<Action> = if state.public_state.current_player == responder {
+            MoveIter::new(&state.public_state, state.current_hidden_state()).collect()
+        } else {
+            vec![fixed.choose_action(&state.public_state, state.current_hidden_state())]
+        };
+        actions
+            .iter()
+            .map(|action| {
+                let (_, undo) = state.transition_with_undo(action);
+                let value = expectimax(state, responder, fixed);
+                state.undo(undo.unwrap());
+                value
+            })
+            .max_by(|a, b| a.total_cmp(b))
+            .unwrap()
+    }
+
+    fn trivial() -> Box<dyn Player> {
+        Box::new(Stateless(TrivialPlayer {}))
+    }
+
+    /// Plays the move at the number of moves so far, wrapping around
+    struct HistoryLengthPlayer {}
+
+    impl StatelessPlayer for HistoryLengthPlayer {
+        fn choose_action(
+            &self,
+            public_state: &PublicState,
+            hidden_state: &PlayerHiddenState,
+        ) -> Action {
+            let moves: Vec<Action> = MoveIter::new(public_state, hidden_state).collect();
+            moves[public_state.action_history.len() % moves.len()].clone()
+        }
+    }
+
+    /// HistoryLengthPlayer counting the moves it observed instead
+    #[derive(Default)]
+    struct CountingPlayer {
+        observed: usize,
+    }
+
+    impl Player for CountingPlayer {
+        fn new_round(&mut self, _seat: PlayerId, _public_state: &PublicState) {
+            self.observed = 0;
+        }
+
+        fn choose_action(
+            &mut self,
+            public_state: &PublicState,
+            hidden_state: &PlayerHiddenState,
+        ) -> Action {
+            let moves: Vec<Action> = MoveIter::new(public_state, hidden_state).collect();
+            moves[self.observed % moves.len()].clone()
+        }
+
+        fn observe(&mut self, _action: &Action, _result: &TransitionResult, _actor: PlayerId) {
+            self.observed += 1;
+        }
+    }
+
+    fn counting() -> Box<dyn Player> {
+        Box::new(CountingPlayer::default())
+    }
+
+    /// Plays a card it does not hold
+    struct IllegalPlayer {}
+
+    impl StatelessPlayer for IllegalPlayer {
+        fn choose_action(
+            &self,
+            _public_state: &PublicState,
+            _hidden_state: &PlayerHiddenState,
+        ) -> Action {
+            Action::PlayCards(u8::MAX - 1, u8::MAX)
+        }
+    }
+
+    #[test]
+    fn test_single_deal_is_expectimax() {
+        for seed in 0..3 {
+            let mut state = GameState::new(&GameConfig::new(2, 6, 1), seed).unwrap();
+            for responder in 0..2 {
+                let response = best_response(responder, &trivial, &[state.clone()], 1);
+                let value = expectimax(&mut state, responder, &TrivialPlayer {});
+                assert!((response.utility - value).abs() < 1e-9);
+
+                // A player that decides from what it observed
+                let response = best_response(responder, &counting, &[state.clone()], 1);
+                let value = expectimax(&mut state, responder, &HistoryLengthPlayer {});
+                assert!((response.utility - value).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn test_hidden_cards() {
+        // The first player cannot tell the deals apart and does no better than when seeing
+        // the other hand in each deal
+        let fixed = TrivialPlayer {};
+        let deals = small_deals();
+        let response = best_response(0, &trivial, &deals, 1);
+        let seeing: f64 = deals
+            .iter()
+            .map(|deal| expectimax(&mut deal.clone(), 0, &fixed))
+            .sum::<f64>()
+            / deals.len() as f64;
+        assert!(response.utility <= seeing + 1e-9);
+        // Information sets span both deals
+        let separately: usize = deals
+            .iter()
+            .map(|deal| best_response(0, &trivial, std::slice::from_ref(deal), 1).num_info_sets)
+            .sum();
+        assert!(response.num_info_sets < separately);
+
+        // No worse than the fixed player playing itself
+        let mut state = deals[0].clone();
+        while !state.public_state.game_complete {
+            let action = fixed.choose_action(&state.public_state, state.current_hidden_state());
+            state.transition(&action);
+        }
+        let scores = state.final_scores().unwrap();
+        let played = best_response(0, &trivial, &deals[..1], 1);
+        assert!(played.utility >= utilities(scores)[0]);
+    }
+
+    #[test]
+    fn test_illegal_moves_forfeit() {
+        let illegal = || Box::new(Stateless(IllegalPlayer {})) as Box<dyn Player>;
+        let deals = small_deals();
+        // The fixed player moves first and forfeits their two cards at once
+        let response = best_response(1, &illegal, &deals, 1);
+        assert_eq!(2.0, response.utility);
+        assert_eq!(0.0, response.score);
+        assert_eq!(0, response.num_info_sets);
+        assert!(exploitability(&illegal, &deals, 1) > 0.0);
+    }
+
+    #[test]
+    fn test_exploitability_falls_with_cfr() {
+        let deals = small_deals();
+        let uniform = || Box::new(StrategyPlayer::new(Strategy::default(), 1)) as Box<dyn Player>;
+        let uniform_exploitability = exploitability(&uniform, &deals, 100);
+        assert!(exploitability(&trivial, &deals, 1) > 1.0);
+
+        let mut cfr = CfrPlus::new(deals.clone());
+        for _ in 0..10 {
+            cfr.iterate();
+        }
+        let strategy = cfr.average_strategy();
+        let solved = || Box::new(StrategyPlayer::new(strategy.clone(), 1)) as Box<dyn Player>;
+        let solved_exploitability = exploitability(&solved, &deals, 100);
+        assert!(
+            solved_exploitability < uniform_exploitability / 4.0,
+            "{} {}",
+            solved_exploitability,
+            uniform_exploitability
+        );
+        assert!(solved_exploitability > -1e-9);
+    }
+}
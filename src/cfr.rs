@@ -0,0 +1,682 @@
+//! Counterfactual regret minimization for small configs. Information sets are keyed by
+//! view::info_set_key, the moves of an information set are in MoveIter order. The deal is a
+//! chance node with every deal equally likely.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_xoshiro::SplitMix64;
+
+use crate::config::GameConfig;
+use crate::engine::{Action, GameState, Orientation, OrientedCard, PlayerId, Undo};
+use crate::search::MoveIter;
+use crate::view::info_set_key;
+
+/// The deals the chance node picks from, each equally likely
+#[derive(Debug, Clone)]
+pub enum Deals {
+    Fixed(Vec<GameState>),
+    /// Every deal of the config, as GameState::new with a random seed. Can only be sampled.
+    Random(GameConfig),
+}
+
+impl Deals {
+    fn sample<R: Rng>(&self, rng: &mut R) -> GameState {
+        match self {
+            Deals::Fixed(deals) => deals.choose(rng).expect("No deals").clone(),
+            Deals::Random(config) => GameState::new(config, rng.gen()).unwrap(),
+        }
+    }
+}
+
+/// Every possible deal of a config: every order of every choice of dealt cards, in every
+/// orientation. Only usable for tiny configs, there are deck!/(deck - dealt)! * 2^dealt.
+pub fn all_deals(config: &GameConfig) -> Vec<GameState> {
+    config.validate().unwrap();
+    let num_dealt = config.num_players * config.hand_size;
+    let mut dealt = vec![];
+    let mut deals = vec![];
+    deal_remaining(
+        config,
+        &mut config.cards(),
+        num_dealt,
+        &mut dealt,
+        &mut deals,
+    );
+    deals
+}
+
+fn deal_remaining(
+    config: &GameConfig,
+    deck: &mut Vec<(u8, u8)>,
+    num_left: usize,
+    dealt: &mut Vec<OrientedCard>,
+    deals: &mut Vec<GameState>,
+) {
+    if num_left == 0 {
+        let hands = dealt
+            .chunks_exact(config.hand_size)
+            .map(|hand| hand.to_vec())
+            .collect();
+        deals.push(GameState::from_hands(config, hands, deals.len() as u64).unwrap());
+        return;
+    }
+    for idx in 0..deck.len() {
+        let card = deck.remove(idx);
+        for orientation in [Orientation::Larger, Orientation::Smaller] {
+            dealt.push(OrientedCard::new(card, orientation));
+            deal_remaining(config, deck, num_left - 1, dealt, deals);
+            dealt.pop();
+        }
+        deck.insert(idx, card);
+    }
+}
+
+/// A player's utility is their score minus the average score of the other players, so the
+/// utilities always sum to zero. With two players it is the score difference.
+pub fn utilities(scores: &[i8]) -> Vec<f64> {
+    let total: f64 = scores.iter().map(|&score| score as f64).sum();
+    let num_others = (scores.len() - 1) as f64;
+    scores
+        .iter()
+        .map(|&score| score as f64 - (total - score as f64) / num_others)
+        .collect()
+}
+
+fn final_utilities(state: &GameState) -> Vec<f64> {
+    let scores = state
+        .final_scores()
+        .unwrap_or_else(|| panic!("Game complete without final scores (seed:{:?})", state.seed));
+    utilities(scores)
+}
+
+fn moves(state: &GameState) -> Vec<Action> {
+    MoveIter::new(&state.public_state, state.current_hidden_state()).collect()
+}
+
+fn current_info_set_key(state: &GameState) -> u64 {
+    let player = state.public_state.current_player;
+    info_set_key(player, &state.public_state, &state.hidden_states[player])
+}
+
+/// Plays action from state, returns the undo
+fn play(state: &mut GameState, action: &Action) -> Undo {
+    let (result, undo) = state.transition_with_undo(action);
+    let Some(undo) = undo else {
+        panic!(
+            "Illegal move ({:?}) (seed:{:?}): {:?}",
+            result, state.seed, action
+        );
+    };
+    undo
+}
+
+/// Key of a fixed information set. Strategies are written with it so read can reject keys
+/// made by a different info_set_key, which would otherwise all be unknown.
+fn key_check() -> u64 {
+    let config = GameConfig {
+        hand_size: 2,
+        ..GameConfig::new(2, 5, 0)
+    };
+    let hands = [[(1, 2), (3, 4)], [(1, 3), (2, 5)]]
+        .iter()
+        .map(|hand| {
+            hand.iter()
+                .map(|&numbers| OrientedCard::new(numbers, Orientation::Larger))
+                .collect()
+        })
+        .collect();
+    current_info_set_key(&GameState::from_hands(&config, hands, 0).unwrap())
+}
+
+/// A probability per move of each information set. Information sets missing from the
+/// strategy are played uniformly at random.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Strategy {
+    probabilities: HashMap<u64, Vec<f32>>,
+}
+
+impl Strategy {
+    pub fn len(&self) -> usize {
+        self.probabilities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.probabilities.is_empty()
+    }
+
+    /// The probabilities of the moves of the information set, in MoveIter order
+    pub fn get(&self, info_set_key: u64) -> Option<&[f32]> {
+        self.probabilities.get(&info_set_key).map(Vec::as_slice)
+    }
+
+    /// Probabilities for num_moves moves, uniform for unknown information sets
+    pub fn probabilities(&self, info_set_key: u64, num_moves: usize) -> Vec<f32> {
+        match self.get(info_set_key) {
+            Some(probabilities) if probabilities.len() == num_moves => probabilities.to_vec(),
+            _ => vec![1.0 / num_moves as f32; num_moves],
+        }
+    }
+
+    /// Writes a header line with key_check, then a line per information set: the key in hex,
+    /// then the probabilities
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writeln!(writer, "info_set_keys {:016x}", key_check())?;
+        let mut keys: Vec<&u64> = self.probabilities.keys().collect();
+        keys.sort();
+        for key in keys {
+            write!(writer, "{:016x}", key)?;
+            for probability in &self.probabilities[key] {
+                write!(writer, " {}", probability)?;
+            }
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+
+    /// Reads what write wrote. Fails if the keys were made by a different info_set_key, or if
+    /// the probabilities of a line are not finite and non-negative with a positive sum.
+    pub fn read<R: BufRead>(reader: R) -> io::Result<Self> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+        let bad_line = |line: &str| invalid(format!("Bad strategy line: {}", line));
+        let mut lines = reader.lines();
+        let header = lines.next().transpose()?.unwrap_or_default();
+        let check = header
+            .strip_prefix("info_set_keys ")
+            .and_then(|check| u64::from_str_radix(check, 16).ok())
+            .ok_or_else(|| invalid(format!("Bad strategy header: {}", header)))?;
+        if check != key_check() {
+            return Err(invalid(format!(
+                "Strategy keys were made by a different info_set_key ({:016x}, expected {:016x})",
+                check,
+                key_check()
+            )));
+        }
+        let mut probabilities = HashMap::new();
+        for line in lines {
+            let line = line?;
+            let mut fields = line.split_whitespace();
+            let Some(key) = fields.next() else {
+                continue;
+            };
+            let key = u64::from_str_radix(key, 16).map_err(|_| bad_line(&line))?;
+            let values = fields
+                .map(|field| field.parse::<f32>())
+                .collect::<Result<Vec<f32>, _>>()
+                .map_err(|_| bad_line(&line))?;
+            // Probabilities must be weights WeightedIndex accepts
+            if values
+                .iter()
+                .any(|value| !value.is_finite() || *value < 0.0)
+                || values.iter().sum::<f32>() <= 0.0
+            {
+                return Err(bad_line(&line));
+            }
+            probabilities.insert(key, values);
+        }
+        Ok(Strategy { probabilities })
+    }
+}
+
+/// Expected utility of each player when every player follows strategy, averaged over deals
+pub fn expected_utilities(strategy: &Strategy, deals: &[GameState]) -> Vec<f64> {
+    let mut totals = vec![0.0; deals[0].num_players()];
+    for deal in deals {
+        let mut state = deal.clone();
+        let values = strategy_values(strategy, &mut state);
+        for (total, value) in totals.iter_mut().zip(values) {
+            *total += value / deals.len() as f64;
+        }
+    }
+    totals
+}
+
+fn strategy_values(strategy: &Strategy, state: &mut GameState) -> Vec<f64> {
+    if state.public_state.game_complete {
+        return final_utilities(state);
+    }
+    let actions = moves(state);
+    let probabilities = strategy.probabilities(current_info_set_key(state), actions.len());
+    let mut values = vec![0.0; state.num_players()];
+    for (action, probability) in actions.iter().zip(probabilities) {
+        if probability <= 0.0 {
+            continue;
+        }
+        let undo = play(state, action);
+        for (value, child) in values.iter_mut().zip(strategy_values(strategy, state)) {
+            *value += probability as f64 * child;
+        }
+        state.undo(undo);
+    }
+    values
+}
+
+#[derive(Debug, Clone)]
+struct InfoSet {
+    regrets: Vec<f64>,
+    strategy_sum: Vec<f64>,
+}
+
+impl InfoSet {
+    fn new(num_moves: usize) -> Self {
+        InfoSet {
+            regrets: vec![0.0; num_moves],
+            strategy_sum: vec![0.0; num_moves],
+        }
+    }
+
+    /// Regret matching, uniform when no move has positive regret
+    fn current_strategy(&self) -> Vec<f64> {
+        let total: f64 = self.regrets.iter().map(|regret| regret.max(0.0)).sum();
+        if total > 0.0 {
+            self.regrets
+                .iter()
+                .map(|regret| regret.max(0.0) / total)
+                .collect()
+        } else {
+            vec![1.0 / self.regrets.len() as f64; self.regrets.len()]
+        }
+    }
+}
+
+fn average_strategy(info_sets: &HashMap<u64, InfoSet>) -> Strategy {
+    let probabilities = info_sets
+        .iter()
+        .map(|(&key, info_set)| {
+            let total: f64 = info_set.strategy_sum.iter().sum();
+            let num_moves = info_set.strategy_sum.len();
+            let probabilities = if total > 0.0 {
+                info_set
+                    .strategy_sum
+                    .iter()
+                    .map(|&sum| (sum / total) as f32)
+                    .collect()
+            } else {
+                vec![1.0 / num_moves as f32; num_moves]
+            };
+            (key, probabilities)
+        })
+        .collect();
+    Strategy { probabilities }
+}
+
+/// CFR+ over every node of every deal. Players update in turn, their regrets summed over the
+/// deals are floored at zero and iteration t adds to the average strategy with weight t.
+pub struct CfrPlus {
+    deals: Vec<GameState>,
+    info_sets: HashMap<u64, InfoSet>,
+    iterations: u64,
+}
+
+impl CfrPlus {
+    pub fn new(deals: Vec<GameState>) -> Self {
+        assert!(!deals.is_empty());
+        CfrPlus {
+            deals,
+            info_sets: HashMap::new(),
+            iterations: 0,
+        }
+    }
+
+    pub fn iterations(&self) -> u64 {
+        self.iterations
+    }
+
+    pub fn num_info_sets(&self) -> usize {
+        self.info_sets.len()
+    }
+
+    /// Every player in turn walks every deal against the strategies as they stand when
+    /// their walk starts, then adds the regrets of all the deals to theirs
+    pub fn iterate(&mut self) {
+        self.iterations += 1;
+        let num_players = self.deals[0].num_players();
+        for traverser in 0..num_players {
+            let mut regrets = HashMap::new();
+            for deal_idx in 0..self.deals.len() {
+                let mut state = self.deals[deal_idx].clone();
+                self.walk(&mut state, traverser, &vec![1.0; num_players], &mut regrets);
+            }
+            for (key, deal_regrets) in regrets {
+                let info_set = self.info_sets.get_mut(&key).unwrap();
+                for (regret, deal_regret) in info_set.regrets.iter_mut().zip(deal_regrets) {
+                    *regret = (*regret + deal_regret).max(0.0);
+                }
+            }
+        }
+    }
+
+    pub fn average_strategy(&self) -> Strategy {
+        average_strategy(&self.info_sets)
+    }
+
+    /// Expected utilities below state, reach is the probability each player plays to it.
+    /// The traverser's regrets are added to regrets, not to their information sets.
+    fn walk(
+        &mut self,
+        state: &mut GameState,
+        traverser: PlayerId,
+        reach: &[f64],
+        regrets: &mut HashMap<u64, Vec<f64>>,
+    ) -> Vec<f64> {
+        if state.public_state.game_complete {
+            return final_utilities(state);
+        }
+        let player = state.public_state.current_player;
+        let key = current_info_set_key(state);
+        let actions = moves(state);
+        let strategy = self
+            .info_sets
+            .entry(key)
+            .or_insert_with(|| InfoSet::new(actions.len()))
+            .current_strategy();
+
+        let mut values = vec![0.0; state.num_players()];
+        let mut action_values = vec![0.0; actions.len()];
+        for (idx, action) in actions.iter().enumerate() {
+            // Lines the other players never play change nothing
+            if player != traverser && strategy[idx] == 0.0 {
+                continue;
+            }
+            let mut child_reach = reach.to_vec();
+            child_reach[player] *= strategy[idx];
+            let undo = play(state, action);
+            let child_values = self.walk(state, traverser, &child_reach, regrets);
+            state.undo(undo);
+            action_values[idx] = child_values[player];
+            for (value, child) in values.iter_mut().zip(child_values) {
+                *value += strategy[idx] * child;
+            }
+        }
+
+        if player == traverser {
+            let others_reach: f64 = (0..reach.len())
+                .filter(|&other| other != player)
+                .map(|other| reach[other])
+                .product();
+            let weight = self.iterations as f64 * reach[player];
+            let info_set = self.info_sets.get_mut(&key).unwrap();
+            let regrets = regrets
+                .entry(key)
+                .or_insert_with(|| vec![0.0; actions.len()]);
+            for idx in 0..actions.len() {
+                regrets[idx] += others_reach * (action_values[idx] - values[player]);
+                info_set.strategy_sum[idx] += weight * strategy[idx];
+            }
+        }
+        values
+    }
+}
+
+/// Monte Carlo CFR with external sampling: each iteration samples a deal per player, who
+/// then tries all of their moves while the other players' moves are sampled from their
+/// current strategy.
+pub struct ExternalSamplingMccfr {
+    deals: Deals,
+    info_sets: HashMap<u64, InfoSet>,
+    rng: SplitMix64,
+    iterations: u64,
+}
+
+impl ExternalSamplingMccfr {
+    pub fn new(deals: Deals, seed: u64) -> Self {
+        ExternalSamplingMccfr {
+            deals,
+            info_sets: HashMap::new(),
+            rng: SplitMix64::seed_from_u64(seed),
+            iterations: 0,
+        }
+    }
+
+    pub fn iterations(&self) -> u64 {
+        self.iterations
+    }
+
+    pub fn num_info_sets(&self) -> usize {
+        self.info_sets.len()
+    }
+
+    pub fn iterate(&mut self) {
+        self.iterations += 1;
+        let mut state = self.deals.sample(&mut self.rng);
+        for traverser in 0..state.num_players() {
+            if traverser > 0 {
+                state = self.deals.sample(&mut self.rng);
+            }
+            self.walk(&mut state, traverser);
+        }
+    }
+
+    pub fn average_strategy(&self) -> Strategy {
+        average_strategy(&self.info_sets)
+    }
+
+    /// Sampled utilities below state
+    fn walk(&mut self, state: &mut GameState, traverser: PlayerId) -> Vec<f64> {
+        if state.public_state.game_complete {
+            return final_utilities(state);
+        }
+        let player = state.public_state.current_player;
+        let key = current_info_set_key(state);
+        let actions = moves(state);
+        let info_set = self
+            .info_sets
+            .entry(key)
+            .or_insert_with(|| InfoSet::new(actions.len()));
+        let strategy = info_set.current_strategy();
+
+        if player != traverser {
+            for (sum, probability) in info_set.strategy_sum.iter_mut().zip(&strategy) {
+                *sum += probability;
+            }
+            let idx = WeightedIndex::new(&strategy).unwrap().sample(&mut self.rng);
+            let undo = play(state, &actions[idx]);
+            let values = self.walk(state, traverser);
+            state.undo(undo);
+            return values;
+        }
+
+        let mut values = vec![0.0; state.num_players()];
+        let mut action_values = vec![0.0; actions.len()];
+        for (idx, action) in actions.iter().enumerate() {
+            let undo = play(state, action);
+            let child_values = self.walk(state, traverser);
+            state.undo(undo);
+            action_values[idx] = child_values[player];
+            for (value, child) in values.iter_mut().zip(child_values) {
+                *value += strategy[idx] * child;
+            }
+        }
+        let info_set = self.info_sets.get_mut(&key).unwrap();
+        for (regret, action_value) in info_set.regrets.iter_mut().zip(action_values) {
+            *regret += action_value - values[player];
+        }
+        values
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::engine::Rules;
+    use crate::search::{solve, TranspositionTable};
+
+    /// Three cards, one each, so the tree of every deal is tiny
+    fn tiny_config() -> GameConfig {
+        GameConfig {
+            num_players: 2,
+            max_card_num: 3,
+            removed_cards: vec![],
+            scout_tokens: 1,
+            hand_size: 1,
+            rules: Rules::default(),
+            first_player: 0,
+        }
+    }
+
+    #[test]
+    fn test_all_deals() {
+        let deals = all_deals(&tiny_config());
+        // 3 * 2 orders of the dealt cards, 2 orientations each
+        assert_eq!(24, deals.len());
+        for (idx, deal) in deals.iter().enumerate() {
+            assert!(!deals[..idx]
+                .iter()
+                .any(|other| other.hidden_states == deal.hidden_states));
+        }
+    }
+
+    #[test]
+    fn test_single_deal_reaches_solved_value() {
+        // With one deal nothing is hidden and the value is the perfect information one
+        let mut state = GameState::new(&GameConfig::new(2, 5, 0), 123).unwrap();
+        let value = solve(&mut state, &mut TranspositionTable::new(1 << 16)).value as f64;
+        let deals = vec![state];
+
+        let mut cfr = CfrPlus::new(deals.clone());
+        for _ in 0..100 {
+            cfr.iterate();
+        }
+        let utilities = expected_utilities(&cfr.average_strategy(), &deals);
+        assert!(
+            (utilities[0] - value).abs() < 0.05,
+            "{:?} {}",
+            utilities,
+            value
+        );
+        assert_eq!(0.0, utilities[0] + utilities[1]);
+
+        let mut mccfr = ExternalSamplingMccfr::new(Deals::Fixed(deals.clone()), 1);
+        for _ in 0..2000 {
+            mccfr.iterate();
+        }
+        let utilities = expected_utilities(&mccfr.average_strategy(), &deals);
+        assert!(
+            (utilities[0] - value).abs() < 0.5,
+            "{:?} {}",
+            utilities,
+            value
+        );
+    }
+
+    /// Four cards, two each. The first player always gets the same hand and cannot tell the
+    /// two deals apart.
+    pub(crate) fn small_deals() -> Vec<GameState> {
+        let config = GameConfig {
+            num_players: 2,
+            max_card_num: 4,
+            removed_cards: vec![(2, 4), (3, 4)],
+            scout_tokens: 0,
+            hand_size: 2,
+            rules: Rules::default(),
+            first_player: 0,
+        };
+        let deals = all_deals(&config);
+        let hand = deals[0].hidden_states[0].clone();
+        deals
+            .into_iter()
+            .filter(|deal| deal.hidden_states[0] == hand)
+            .take(2)
+            .collect()
+    }
+
+    #[test]
+    fn test_chance_deals() {
+        let deals = small_deals();
+        assert_eq!(
+            current_info_set_key(&deals[0]),
+            current_info_set_key(&deals[1])
+        );
+        let mut cfr = CfrPlus::new(deals.clone());
+        for _ in 0..20 {
+            cfr.iterate();
+        }
+        let strategy = cfr.average_strategy();
+        for probabilities in strategy.probabilities.values() {
+            let total: f32 = probabilities.iter().sum();
+            assert!((total - 1.0).abs() < 1e-4);
+        }
+        let cfr_utilities = expected_utilities(&strategy, &deals);
+
+        let mut mccfr = ExternalSamplingMccfr::new(Deals::Fixed(deals.clone()), 2);
+        for _ in 0..2000 {
+            mccfr.iterate();
+        }
+        let mccfr_utilities = expected_utilities(&mccfr.average_strategy(), &deals);
+        assert!(
+            (cfr_utilities[0] - mccfr_utilities[0]).abs() < 0.2,
+            "{:?} {:?}",
+            cfr_utilities,
+            mccfr_utilities
+        );
+
+        // Sampling every deal of a config reaches every information set of it
+        let mut mccfr = ExternalSamplingMccfr::new(Deals::Random(tiny_config()), 3);
+        for _ in 0..2000 {
+            mccfr.iterate();
+        }
+        let mut cfr = CfrPlus::new(all_deals(&tiny_config()));
+        cfr.iterate();
+        assert_eq!(cfr.num_info_sets(), mccfr.num_info_sets());
+    }
+
+    #[test]
+    fn test_regrets_summed_over_deals() {
+        // Regrets are floored once the regrets of every deal are summed, so the order of the
+        // deals changes nothing
+        let deals = small_deals();
+        let mut forward = CfrPlus::new(deals.clone());
+        let mut backward = CfrPlus::new(deals.into_iter().rev().collect());
+        for _ in 0..5 {
+            forward.iterate();
+            backward.iterate();
+        }
+        assert_eq!(forward.num_info_sets(), backward.num_info_sets());
+        for (key, info_set) in &forward.info_sets {
+            let other = &backward.info_sets[key];
+            for (a, b) in info_set.regrets.iter().zip(&other.regrets) {
+                assert!(
+                    (a - b).abs() < 1e-9,
+                    "{:?} {:?}",
+                    info_set.regrets,
+                    other.regrets
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_strategy_round_trip() {
+        let deals = all_deals(&tiny_config());
+        let mut cfr = CfrPlus::new(deals);
+        for _ in 0..10 {
+            cfr.iterate();
+        }
+        let strategy = cfr.average_strategy();
+        let mut written = vec![];
+        strategy.write(&mut written).unwrap();
+        let read = Strategy::read(&written[..]).unwrap();
+        assert_eq!(strategy.len(), read.len());
+        for (key, probabilities) in &strategy.probabilities {
+            for (a, b) in probabilities.iter().zip(read.get(*key).unwrap()) {
+                assert!((a - b).abs() < 1e-6);
+            }
+        }
+        let header = format!("info_set_keys {:016x}\n", key_check());
+        assert!(Strategy::read(format!("{}12 0.5 x\n", header).as_bytes()).is_err());
+        assert!(Strategy::read(format!("{}zz 1\n", header).as_bytes()).is_err());
+        assert!(Strategy::read(format!("{}12 0.5 0.5\n", header).as_bytes()).is_ok());
+        for values in ["", "0 0", "-0.5 1", "NaN 1", "inf 1"] {
+            let line = format!("{}12 {}\n", header, values);
+            assert!(Strategy::read(line.as_bytes()).is_err(), "{}", values);
+        }
+        // Saved strategies depend on the keys, changing them makes the files unreadable
+        assert_eq!(0x0ca5491da46b3c6d, key_check());
+        // Keys of another hash
+        assert!(Strategy::read(&b"info_set_keys 0123456789abcdef\n12 1\n"[..]).is_err());
+        assert!(Strategy::read(&b"12 1\n"[..]).is_err());
+    }
+}
@@ -0,0 +1,299 @@
+use std::fmt;
+
+use crate::engine::{PlayerId, Rules, MAX_PLAYERS};
+
+/// Card numbers go from 1 to max_card_num, scores and card counts must fit in an i8/u8
+pub const MAX_CARD_NUM: u8 = 39;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GameConfig {
+    pub num_players: usize,
+    /// The deck has one card for every pair of numbers in 1..=max_card_num
+    pub max_card_num: u8,
+    /// Cards taken out of the deck before shuffling, as (smaller number, larger number)
+    pub removed_cards: Vec<(u8, u8)>,
+    /// Tokens each player starts the round with
+    pub scout_tokens: u8,
+    /// Cards dealt to each player, the rest of the deck is not used
+    pub hand_size: usize,
+    pub rules: Rules,
+    /// Chooses orientation first and makes the first play
+    pub first_player: PlayerId,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    NumPlayers(usize),
+    MaxCardNum(u8),
+    /// Not a card of the deck, or removed twice
+    BadRemovedCard((u8, u8)),
+    EmptyHand,
+    NotEnoughCards {
+        needed: usize,
+        available: usize,
+    },
+    /// Scores and card counts would not fit in their integer types
+    TooManyCards(usize),
+    FirstPlayer(PlayerId),
+    /// The dealt cards and starting tokens could add up to a score beyond i8::MAX
+    TooManyTokens(usize),
+    /// A different number of players was given to play a game of the config
+    PlayerCount {
+        expected: usize,
+        given: usize,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::NumPlayers(n) => {
+                write!(f, "{} players, must be 2 to {}", n, MAX_PLAYERS)
+            }
+            ConfigError::MaxCardNum(n) => {
+                write!(f, "max card number {}, must be 2 to {}", n, MAX_CARD_NUM)
+            }
+            ConfigError::BadRemovedCard((first, second)) => {
+                write!(f, "removed card {}|{} is not in the deck", first, second)
+            }
+            ConfigError::EmptyHand => write!(f, "hand size must be at least 1"),
+            ConfigError::NotEnoughCards { needed, available } => write!(
+                f,
+                "dealing needs {} cards but the deck has {}",
+                needed, available
+            ),
+            ConfigError::TooManyCards(n) => write!(f, "{} cards dealt, at most 127", n),
+            ConfigError::FirstPlayer(player) => {
+                write!(f, "first player {} is not at the table", player)
+            }
+            ConfigError::TooManyTokens(n) => {
+                write!(f, "{} cards and scout tokens in play, at most 127", n)
+            }
+            ConfigError::PlayerCount { expected, given } => {
+                write!(f, "{} players given for a game of {}", given, expected)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl GameConfig {
+    /// The original variant: the deck is trimmed so it can be dealt twice over and only half of
+    /// it is dealt. Two players with max_card_num 10 get 11 cards each.
+    pub fn new(num_players: usize, max_card_num: u8, scout_tokens: u8) -> Self {
+        let all_cards = all_cards(max_card_num);
+        let deal_multiple = 2 * num_players.max(1);
+        let num_removed = all_cards.len() % deal_multiple;
+        // Cards are dropped from the high end of the deck
+        let removed_cards = all_cards[all_cards.len() - num_removed..].to_vec();
+
+        GameConfig {
+            num_players,
+            max_card_num,
+            removed_cards,
+            scout_tokens,
+            hand_size: all_cards.len() / deal_multiple,
+            rules: Rules::default(),
+            first_player: 0,
+        }
+    }
+
+    /// The retail game for 3 to 5 players, the whole deck is dealt. With 3 players every card
+    /// with a 10 is removed, with 4 players the 9|10 card is removed.
+    pub fn published(num_players: usize) -> Result<Self, ConfigError> {
+        let removed_cards = match num_players {
+            3 => (1..10).map(|n| (n, 10)).collect(),
+            4 => vec![(9, 10)],
+            5 => vec![],
+            _ => return Err(ConfigError::NumPlayers(num_players)),
+        };
+        let config = GameConfig {
+            num_players,
+            max_card_num: 10,
+            hand_size: (45 - removed_cards.len()) / num_players,
+            removed_cards,
+            scout_tokens: 0,
+            rules: Rules::published(),
+            first_player: 0,
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if !(2..=MAX_PLAYERS).contains(&self.num_players) {
+            return Err(ConfigError::NumPlayers(self.num_players));
+        }
+        if !(2..=MAX_CARD_NUM).contains(&self.max_card_num) {
+            return Err(ConfigError::MaxCardNum(self.max_card_num));
+        }
+        for (idx, &(first, second)) in self.removed_cards.iter().enumerate() {
+            if first == 0
+                || first >= second
+                || second > self.max_card_num
+                || self.removed_cards[..idx].contains(&(first, second))
+            {
+                return Err(ConfigError::BadRemovedCard((first, second)));
+            }
+        }
+        if self.hand_size == 0 {
+            return Err(ConfigError::EmptyHand);
+        }
+
+        let needed = self.hand_size * self.num_players;
+        let available = self.deck_size();
+        if needed > available {
+            return Err(ConfigError::NotEnoughCards { needed, available });
+        }
+        // A player can end up holding every dealt card, or winning all of them
+        if needed > i8::MAX as usize {
+            return Err(ConfigError::TooManyCards(needed));
+        }
+        // Or every starting token on top of that
+        let with_tokens = needed + self.num_players * self.scout_tokens as usize;
+        if with_tokens > i8::MAX as usize {
+            return Err(ConfigError::TooManyTokens(with_tokens));
+        }
+        if self.first_player >= self.num_players {
+            return Err(ConfigError::FirstPlayer(self.first_player));
+        }
+        Ok(())
+    }
+
+    /// Number of cards in the deck after removing cards
+    pub fn deck_size(&self) -> usize {
+        let max_card_num = self.max_card_num as usize;
+        max_card_num * (max_card_num.max(1) - 1) / 2 - self.removed_cards.len()
+    }
+
+    /// Every card of the deck as (smaller number, larger number), in deck order
+    pub fn cards(&self) -> Vec<(u8, u8)> {
+        all_cards(self.max_card_num)
+            .into_iter()
+            .filter(|card| !self.removed_cards.contains(card))
+            .collect()
+    }
+}
+
+fn all_cards(max_card_num: u8) -> Vec<(u8, u8)> {
+    (1..max_card_num)
+        .flat_map(|first| (first + 1..=max_card_num).map(move |second| (first, second)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_original_variant() {
+        let config = GameConfig::new(2, 10, 3);
+        assert_eq!(Ok(()), config.validate());
+        assert_eq!(vec![(9, 10)], config.removed_cards);
+        assert_eq!(44, config.deck_size());
+        assert_eq!(11, config.hand_size);
+
+        let config = GameConfig::new(3, 10, 3);
+        assert_eq!(42, config.deck_size());
+        assert_eq!(7, config.hand_size);
+        assert_eq!(42, config.cards().len());
+    }
+
+    #[test]
+    fn test_published() {
+        for (num_players, deck_size, hand_size) in [(3, 36, 12), (4, 44, 11), (5, 45, 9)] {
+            let config = GameConfig::published(num_players).unwrap();
+            assert_eq!(deck_size, config.deck_size());
+            assert_eq!(deck_size, config.cards().len());
+            assert_eq!(hand_size, config.hand_size);
+        }
+        assert!(!GameConfig::published(3).unwrap().cards().contains(&(1, 10)));
+        assert_eq!(
+            Err(ConfigError::NumPlayers(2)),
+            GameConfig::published(2).map(|_| ())
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        let config = GameConfig::new(2, 10, 3);
+        let check = |config: GameConfig| config.validate();
+
+        assert_eq!(
+            Err(ConfigError::NumPlayers(6)),
+            check(GameConfig {
+                num_players: 6,
+                ..config.clone()
+            })
+        );
+        assert_eq!(
+            Err(ConfigError::MaxCardNum(40)),
+            check(GameConfig {
+                max_card_num: 40,
+                ..config.clone()
+            })
+        );
+        assert_eq!(
+            Err(ConfigError::BadRemovedCard((9, 10))),
+            check(GameConfig {
+                removed_cards: vec![(9, 10), (9, 10)],
+                ..config.clone()
+            })
+        );
+        assert_eq!(
+            Err(ConfigError::BadRemovedCard((3, 3))),
+            check(GameConfig {
+                removed_cards: vec![(3, 3)],
+                ..config.clone()
+            })
+        );
+        assert_eq!(
+            Err(ConfigError::EmptyHand),
+            check(GameConfig {
+                hand_size: 0,
+                ..config.clone()
+            })
+        );
+        assert_eq!(
+            Err(ConfigError::NotEnoughCards {
+                needed: 46,
+                available: 44
+            }),
+            check(GameConfig {
+                hand_size: 23,
+                ..config.clone()
+            })
+        );
+        assert_eq!(
+            Err(ConfigError::TooManyCards(190)),
+            check(GameConfig {
+                max_card_num: 20,
+                removed_cards: vec![],
+                hand_size: 95,
+                ..config.clone()
+            })
+        );
+        assert_eq!(
+            Err(ConfigError::FirstPlayer(2)),
+            check(GameConfig {
+                first_player: 2,
+                ..config.clone()
+            })
+        );
+        assert_eq!(
+            Err(ConfigError::TooManyTokens(2 * 11 + 2 * 53)),
+            check(GameConfig::new(2, 10, 53))
+        );
+        assert!(check(GameConfig::new(2, 10, 52)).is_ok());
+        assert_eq!(
+            "dealing needs 46 cards but the deck has 44",
+            check(GameConfig {
+                hand_size: 23,
+                ..config
+            })
+            .unwrap_err()
+            .to_string()
+        );
+    }
+}
@@ -0,0 +1,246 @@
+use std::collections::HashSet;
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::config::GameConfig;
+use crate::engine::{Action, FlipHand, GameState, Orientation, OrientedCard};
+use crate::view::PlayerView;
+
+/// Sampled deals rejected before sample_determinization gives up
+pub const MAX_SAMPLE_ATTEMPTS: usize = 1000;
+
+/// Where a card in hand came from while replaying the history
+#[derive(Debug, Clone, Copy)]
+enum Slot {
+    /// The card dealt at this index of the player's hand
+    Dealt(usize),
+    Scouted,
+}
+
+/// Per player, the dealt hand as far as the view tells it, in dealt orientation. The cards a
+/// player showed are known from the shown sets, the viewing player knows their own hand, the
+/// rest is None.
+fn known_deal(view: &PlayerView, config: &GameConfig) -> Vec<Vec<Option<OrientedCard>>> {
+    let public_state = view.public_state();
+    let num_players = public_state.num_players();
+    let mut deal = vec![vec![None; config.hand_size]; num_players];
+    let mut slots: Vec<Vec<Slot>> =
+        vec![(0..config.hand_size).map(Slot::Dealt).collect(); num_players];
+    let mut flipped = vec![false; num_players];
+    let mut shown_sets = public_state.shown_sets.iter();
+
+    // Shown cards go back to the orientation they were dealt in
+    let reveal = |deal: &mut Vec<Vec<Option<OrientedCard>>>,
+                  player: usize,
+                  slot: Slot,
+                  card: OrientedCard,
+                  flipped: bool| {
+        if let Slot::Dealt(idx) = slot {
+            deal[player][idx] = Some(if flipped { card.flip() } else { card });
+        }
+    };
+
+    for (actor, action, _) in &public_state.action_history {
+        let actor = *actor;
+        let shown = match action {
+            Action::ChooseOrientation(FlipHand::DoFlip) => {
+                flipped[actor] = true;
+                None
+            }
+            Action::ChooseOrientation(FlipHand::DoNotFlip) => None,
+            Action::PlayCards(start_idx, end_idx) => Some((*start_idx, *end_idx)),
+            Action::PlayScoutToken((_, insertion_idx, _)) => {
+                slots[actor].insert(*insertion_idx as usize, Slot::Scouted);
+                None
+            }
+            Action::ScoutAndShow((_, insertion_idx, _), play) => {
+                slots[actor].insert(*insertion_idx as usize, Slot::Scouted);
+                Some(*play)
+            }
+        };
+        if let Some((start_idx, end_idx)) = shown {
+            let shown_set = shown_sets
+                .next()
+                .expect("Shown sets missing from the history");
+            let shown_slots: Vec<Slot> = slots[actor]
+                .drain(start_idx as usize..end_idx as usize)
+                .collect();
+            for (slot, card) in shown_slots.into_iter().zip(shown_set) {
+                reveal(&mut deal, actor, slot, *card, flipped[actor]);
+            }
+        }
+    }
+
+    let player = view.player();
+    for (slot, card) in slots[player].iter().zip(&view.hidden_state().hand) {
+        reveal(&mut deal, player, *slot, *card, flipped[player]);
+    }
+    deal
+}
+
+/// A deal consistent with the cards the view has seen, the unseen cards are drawn uniformly
+/// from the rest of the deck, in random orientation. Replaying the history from it is legal,
+/// but it can still disagree with the view, for example a player could be left without a
+/// legal move where the real game went on. See sample_determinization.
+pub fn sample_deal<R: Rng>(
+    view: &PlayerView,
+    config: &GameConfig,
+    rng: &mut R,
+) -> Vec<Vec<OrientedCard>> {
+    let deal = known_deal(view, config);
+    let seen: HashSet<(u8, u8)> = deal
+        .iter()
+        .flatten()
+        .flatten()
+        .map(|card| card.numbers())
+        .collect();
+    let mut unseen: Vec<(u8, u8)> = config
+        .cards()
+        .into_iter()
+        .filter(|card| !seen.contains(card))
+        .collect();
+    unseen.shuffle(rng);
+
+    let mut unseen = unseen.into_iter();
+    deal.into_iter()
+        .map(|hand| {
+            hand.into_iter()
+                .map(|card| {
+                    card.unwrap_or_else(|| {
+                        let orientation = if rng.gen_bool(0.5) {
+                            Orientation::Larger
+                        } else {
+                            Orientation::Smaller
+                        };
+                        OrientedCard::new(
+                            unseen.next().expect("Deck too small for the deal"),
+                            orientation,
+                        )
+                    })
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// A random complete game state the viewing player cannot tell apart from the real one: the
+/// history replays from a sampled deal to the same public state and the same hand for the
+/// player. Deals that do not replay to the view are rejected, None after
+/// MAX_SAMPLE_ATTEMPTS of them. config is the config the game was dealt with.
+pub fn sample_determinization<R: Rng>(
+    view: &PlayerView,
+    config: &GameConfig,
+    rng: &mut R,
+) -> Option<GameState> {
+    let public_state = view.public_state();
+    'attempts: for _ in 0..MAX_SAMPLE_ATTEMPTS {
+        let deal = sample_deal(view, config, rng);
+        let mut state = GameState::from_hands(config, deal, rng.gen()).unwrap();
+        for (_, action, result) in &public_state.action_history {
+            if state.transition(action) != *result {
+                continue 'attempts;
+            }
+        }
+        if state.public_state == *public_state
+            && state.hidden_states[view.player()] == *view.hidden_state()
+        {
+            return Some(state);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::TransitionResult;
+    use crate::search::MoveIter;
+    use rand::seq::IteratorRandom;
+    use rand::SeedableRng;
+    use rand_xoshiro::SplitMix64;
+
+    /// Random games of each config, stopped at random points
+    fn random_states(configs: &[GameConfig], rng: &mut SplitMix64) -> Vec<(GameConfig, GameState)> {
+        let mut states = vec![];
+        for config in configs {
+            for seed in 0..6 {
+                let mut state = GameState::new(config, seed).unwrap();
+                let num_moves = rng.gen_range(0..40);
+                for _ in 0..num_moves {
+                    if state.public_state.game_complete {
+                        break;
+                    }
+                    let action = MoveIter::new(&state.public_state, state.current_hidden_state())
+                        .choose(rng)
+                        .unwrap();
+                    state.transition(&action);
+                }
+                states.push((config.clone(), state));
+            }
+        }
+        states
+    }
+
+    #[test]
+    fn test_sampled_deals_replay_legally() {
+        let mut rng = SplitMix64::seed_from_u64(1);
+        let configs = [GameConfig::new(2, 10, 3), GameConfig::published(3).unwrap()];
+        for (config, state) in random_states(&configs, &mut rng) {
+            for player in 0..state.num_players() {
+                let view = PlayerView::new(&state, player);
+                for _ in 0..5 {
+                    let deal = sample_deal(&view, &config, &mut rng);
+                    let mut dealt: Vec<(u8, u8)> =
+                        deal.iter().flatten().map(|card| card.numbers()).collect();
+                    dealt.sort();
+                    dealt.dedup();
+                    assert_eq!(config.num_players * config.hand_size, dealt.len());
+
+                    let mut sample = GameState::from_hands(&config, deal, 0).unwrap();
+                    for (actor, action, _) in &state.public_state.action_history {
+                        assert_eq!(*actor, sample.public_state.current_player);
+                        let result = sample.transition(action);
+                        assert!(
+                            !matches!(result, TransitionResult::IllegalMove(_)),
+                            "{:?}",
+                            action
+                        );
+                        if sample.public_state.game_complete {
+                            break;
+                        }
+                    }
+                    assert_eq!(state.hidden_states[player], sample.hidden_states[player]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_sample_determinization() {
+        let mut rng = SplitMix64::seed_from_u64(2);
+        let configs = [
+            GameConfig::new(2, 10, 3),
+            GameConfig::new(4, 10, 2),
+            GameConfig::published(5).unwrap(),
+        ];
+        let mut num_differing = 0;
+        for (config, state) in random_states(&configs, &mut rng) {
+            for player in 0..state.num_players() {
+                let view = PlayerView::new(&state, player);
+                let sample = sample_determinization(&view, &config, &mut rng).unwrap();
+                assert_eq!(view, PlayerView::new(&sample, player));
+                for (other, hand) in sample.hidden_states.iter().enumerate() {
+                    // Cards the player saw scouted are where they were put
+                    for (card, known_card) in hand.hand.iter().zip(view.known_hand(other)) {
+                        if let Some(known_card) = known_card {
+                            assert_eq!(*card, known_card);
+                        }
+                    }
+                }
+                num_differing += (sample.hidden_states != state.hidden_states) as usize;
+            }
+        }
+        assert!(num_differing > 0);
+    }
+}
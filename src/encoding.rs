@@ -0,0 +1,612 @@
+use crate::config::GameConfig;
+use crate::engine::{
+    Action, FlipHand, GameState, Orientation, OrientedCard, PickedCard, PlayerHiddenState,
+    PlayerId, PublicState, ScoutTokenRule,
+};
+use crate::search::MoveIter;
+
+/// Bumped whenever the observation layout changes, it is the first value of every observation
+pub const OBSERVATION_VERSION: u32 = 2;
+
+/// Values per card slot, a one-hot of the top number then one of the bottom number
+fn card_slot_size(max_card_num: u8) -> usize {
+    2 * max_card_num as usize
+}
+
+const SEAT_SIZE: usize = 6;
+const HISTORY_ENTRY_SIZE: usize = 11;
+
+/// Encodes what a seat can see as a flat Vec<f32>. Seats are relative to the observer: seat 0
+/// is the observer, seat 1 the next player to act after them and so on. Counts are raw, not
+/// scaled. The layout for version 2 is
+///   1 value: OBSERVATION_VERSION
+///   2 values: orientation chosen, game complete
+///   max_hand_len card slots: the observer's hand in order
+///   max_hand_len card slots: the board in order
+///   num_players * 6 values, per seat: card count, scout tokens, won cards, Scout & Show used,
+///     owns the board, is to act
+///   history_len * 11 values, per action from the latest back: present, seat of the actor,
+///     one-hot of the action kind (4 values), flip or picked the last card, insertion index,
+///     play start, play end, scouted as Smaller. Scout & Show uses all three indices, its play
+///     indices are into the hand after the insertion.
+/// A card slot is a one-hot of the top number then one of the bottom number, all zeros when
+/// empty. The perfect information variant appends the hands of seats 1 and up as card slots.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ObservationEncoder {
+    pub num_players: usize,
+    pub max_card_num: u8,
+    pub max_hand_len: usize,
+    /// Number of the latest actions included
+    pub history_len: usize,
+}
+
+impl ObservationEncoder {
+    pub fn new(config: &GameConfig, history_len: usize) -> Self {
+        ObservationEncoder {
+            num_players: config.num_players,
+            max_card_num: config.max_card_num,
+            max_hand_len: ActionSpace::new(config).max_hand_len,
+            history_len,
+        }
+    }
+
+    fn hand_size(&self) -> usize {
+        self.max_hand_len * card_slot_size(self.max_card_num)
+    }
+
+    pub fn size(&self) -> usize {
+        3 + 2 * self.hand_size()
+            + self.num_players * SEAT_SIZE
+            + self.history_len * HISTORY_ENTRY_SIZE
+    }
+
+    pub fn perfect_information_size(&self) -> usize {
+        self.size() + (self.num_players - 1) * self.hand_size()
+    }
+
+    fn seat(&self, observer: PlayerId, player: PlayerId) -> usize {
+        (player + self.num_players - observer) % self.num_players
+    }
+
+    /// Writes the card slots of cards into out, which holds max_hand_len slots
+    fn write_cards(&self, out: &mut [f32], cards: &[OrientedCard]) {
+        debug_assert!(cards.len() <= self.max_hand_len);
+        let max_card_num = self.max_card_num as usize;
+        out.fill(0.0);
+        for (slot, card) in out
+            .chunks_exact_mut(card_slot_size(self.max_card_num))
+            .zip(cards)
+        {
+            slot[card.top() as usize - 1] = 1.0;
+            slot[max_card_num + card.bottom() as usize - 1] = 1.0;
+        }
+    }
+
+    fn write_history_entry(
+        &self,
+        out: &mut [f32],
+        observer: PlayerId,
+        actor: PlayerId,
+        action: &Action,
+    ) {
+        let picked_last = |picked_card: &PickedCard| (*picked_card == PickedCard::LastCard) as u8;
+        let smaller = |orientation: &Orientation| (*orientation == Orientation::Smaller) as u8;
+        // (kind, flip or picked last, insertion index, play start, play end, scouted as Smaller)
+        let (kind, flag, insertion_idx, start_idx, end_idx, scouted_smaller) = match action {
+            Action::ChooseOrientation(do_flip) => {
+                (0, (*do_flip == FlipHand::DoFlip) as u8, 0, 0, 0, 0)
+            }
+            Action::PlayCards(start_idx, end_idx) => (1, 0, 0, *start_idx, *end_idx, 0),
+            Action::PlayScoutToken((picked_card, insertion_idx, orientation)) => (
+                2,
+                picked_last(picked_card),
+                *insertion_idx,
+                0,
+                0,
+                smaller(orientation),
+            ),
+            Action::ScoutAndShow(
+                (picked_card, insertion_idx, orientation),
+                (start_idx, end_idx),
+            ) => (
+                3,
+                picked_last(picked_card),
+                *insertion_idx,
+                *start_idx,
+                *end_idx,
+                smaller(orientation),
+            ),
+        };
+        out.fill(0.0);
+        out[0] = 1.0;
+        out[1] = self.seat(observer, actor) as f32;
+        out[2 + kind] = 1.0;
+        for (value, field) in
+            out[6..]
+                .iter_mut()
+                .zip([flag, insertion_idx, start_idx, end_idx, scouted_smaller])
+        {
+            *value = field as f32;
+        }
+    }
+
+    /// What the observer can see, hidden_state must be the observer's
+    pub fn encode(
+        &self,
+        public_state: &PublicState,
+        hidden_state: &PlayerHiddenState,
+        observer: PlayerId,
+    ) -> Vec<f32> {
+        let mut encoded = vec![0.0; self.size()];
+        self.encode_into(public_state, hidden_state, observer, &mut encoded);
+        encoded
+    }
+
+    /// As encode, writing into out which must have size() values
+    pub fn encode_into(
+        &self,
+        public_state: &PublicState,
+        hidden_state: &PlayerHiddenState,
+        observer: PlayerId,
+        out: &mut [f32],
+    ) {
+        debug_assert_eq!(self.num_players, public_state.num_players());
+        assert_eq!(self.size(), out.len());
+        out[0] = OBSERVATION_VERSION as f32;
+        out[1] = public_state.orientation_chosen as u8 as f32;
+        out[2] = public_state.game_complete as u8 as f32;
+        let (hand, rest) = out[3..].split_at_mut(self.hand_size());
+        self.write_cards(hand, &hidden_state.hand);
+        let (board, rest) = rest.split_at_mut(self.hand_size());
+        self.write_cards(board, &public_state.board);
+
+        let (seats, history) = rest.split_at_mut(self.num_players * SEAT_SIZE);
+        for (seat, values) in seats.chunks_exact_mut(SEAT_SIZE).enumerate() {
+            let player = (observer + seat) % self.num_players;
+            let fields = [
+                public_state.card_counts[player],
+                public_state.scout_token_counts[player],
+                public_state.won_cards[player],
+                public_state.scout_and_show_used[player] as u8,
+                (public_state.board_owner == Some(player)) as u8,
+                (public_state.current_player == player) as u8,
+            ];
+            for (value, field) in values.iter_mut().zip(fields) {
+                *value = field as f32;
+            }
+        }
+
+        history.fill(0.0);
+        for (entry, (actor, action, _)) in history
+            .chunks_exact_mut(HISTORY_ENTRY_SIZE)
+            .zip(public_state.action_history.iter().rev())
+        {
+            self.write_history_entry(entry, observer, *actor, action);
+        }
+    }
+
+    /// The observation followed by every other hand, for perfect information training
+    pub fn encode_perfect_information(&self, state: &GameState, observer: PlayerId) -> Vec<f32> {
+        let mut encoded = vec![0.0; self.perfect_information_size()];
+        self.encode_perfect_information_into(state, observer, &mut encoded);
+        encoded
+    }
+
+    /// As encode_perfect_information, writing into out which must have
+    /// perfect_information_size() values
+    pub fn encode_perfect_information_into(
+        &self,
+        state: &GameState,
+        observer: PlayerId,
+        out: &mut [f32],
+    ) {
+        assert_eq!(self.perfect_information_size(), out.len());
+        let (observation, hands) = out.split_at_mut(self.size());
+        self.encode_into(
+            &state.public_state,
+            &state.hidden_states[observer],
+            observer,
+            observation,
+        );
+        for (seat, hand) in hands.chunks_exact_mut(self.hand_size()).enumerate() {
+            let player = (observer + seat + 1) % self.num_players;
+            self.write_cards(hand, &state.hidden_states[player].hand);
+        }
+    }
+}
+
+/// Numbers every action of a game with a stable index in 0..size(), for policies that output
+/// a fixed size vector. The indices are laid out as
+///   2 orientation choices, DoFlip then DoNotFlip
+///   every PlayCards(start, end) with start < end <= max_hand_len, by start then end
+///   every PlayScoutToken, by picked card, insertion index then orientation (Larger first)
+///   every ScoutAndShow, by scout as above then play as for PlayCards
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ActionSpace {
+    /// No hand can hold more cards. Every other player keeps at least a card while the round
+    /// goes on, and a scouted card comes from the board. With ScoutTokenRule::Spend a hand
+    /// also gains at most a card per scout token, plus one held during Scout & Show.
+    pub max_hand_len: usize,
+}
+
+impl ActionSpace {
+    pub fn new(config: &GameConfig) -> Self {
+        Self::from_counts(
+            config.num_players,
+            config.num_players * config.hand_size,
+            config.rules.scout_tokens,
+            config.scout_tokens as usize,
+        )
+    }
+
+    /// The same space as new for the config the state was dealt from
+    pub fn from_public_state(public_state: &PublicState) -> Self {
+        // Every dealt card is in a hand, on the board, or won
+        let total_cards = public_state.board.len()
+            + public_state
+                .card_counts
+                .iter()
+                .chain(public_state.won_cards.iter())
+                .map(|&count| count as usize)
+                .sum::<usize>();
+        // With ScoutTokenRule::Spend every token dealt is held or was spent scouting
+        let num_scouts = public_state
+            .action_history
+            .iter()
+            .filter(|(_, action, _)| matches!(action, Action::PlayScoutToken(_)))
+            .count();
+        let total_tokens = num_scouts
+            + public_state
+                .scout_token_counts
+                .iter()
+                .map(|&count| count as usize)
+                .sum::<usize>();
+        let num_players = public_state.num_players();
+        Self::from_counts(
+            num_players,
+            total_cards,
+            public_state.rules.scout_tokens,
+            total_tokens / num_players,
+        )
+    }
+
+    fn from_counts(
+        num_players: usize,
+        total_cards: usize,
+        scout_token_rule: ScoutTokenRule,
+        scout_tokens: usize,
+    ) -> Self {
+        let max_hand_len = total_cards + 1 - num_players;
+        ActionSpace {
+            max_hand_len: match scout_token_rule {
+                ScoutTokenRule::Spend => {
+                    max_hand_len.min(total_cards / num_players + scout_tokens + 1)
+                }
+                ScoutTokenRule::AwardOwner => max_hand_len,
+            },
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.scout_and_show_offset() + self.num_scouts() * self.num_plays()
+    }
+
+    fn num_plays(&self) -> usize {
+        self.max_hand_len * (self.max_hand_len + 1) / 2
+    }
+
+    fn num_scouts(&self) -> usize {
+        // The board is not empty, so the hand has at most max_hand_len - 1 cards
+        4 * self.max_hand_len
+    }
+
+    fn play_offset(&self) -> usize {
+        2
+    }
+
+    fn scout_offset(&self) -> usize {
+        self.play_offset() + self.num_plays()
+    }
+
+    fn scout_and_show_offset(&self) -> usize {
+        self.scout_offset() + self.num_scouts()
+    }
+
+    fn play_index(&self, (start_idx, end_idx): (u8, u8)) -> Option<usize> {
+        let (start_idx, end_idx) = (start_idx as usize, end_idx as usize);
+        if start_idx >= end_idx || end_idx > self.max_hand_len {
+            return None;
+        }
+        // Ranges starting before start_idx come first
+        let preceding = start_idx * self.max_hand_len - start_idx * start_idx.saturating_sub(1) / 2;
+        Some(preceding + end_idx - start_idx - 1)
+    }
+
+    fn play_from_index(&self, mut idx: usize) -> (u8, u8) {
+        for start_idx in 0..self.max_hand_len {
+            let num_ends = self.max_hand_len - start_idx;
+            if idx < num_ends {
+                return (start_idx as u8, (start_idx + idx + 1) as u8);
+            }
+            idx -= num_ends;
+        }
+        panic!("Play index out of range");
+    }
+
+    fn scout_index(
+        &self,
+        (picked_card, insertion_idx, orientation): &(PickedCard, u8, Orientation),
+    ) -> Option<usize> {
+        let insertion_idx = *insertion_idx as usize;
+        if insertion_idx >= self.max_hand_len {
+            return None;
+        }
+        let picked_card = match picked_card {
+            PickedCard::FirstCard => 0,
+            PickedCard::LastCard => 1,
+        };
+        let orientation = match orientation {
+            Orientation::Larger => 0,
+            Orientation::Smaller => 1,
+        };
+        Some((picked_card * self.max_hand_len + insertion_idx) * 2 + orientation)
+    }
+
+    fn scout_from_index(&self, idx: usize) -> (PickedCard, u8, Orientation) {
+        let picked_card = if idx / (2 * self.max_hand_len) == 0 {
+            PickedCard::FirstCard
+        } else {
+            PickedCard::LastCard
+        };
+        let insertion_idx = ((idx / 2) % self.max_hand_len) as u8;
+        let orientation = if idx.is_multiple_of(2) {
+            Orientation::Larger
+        } else {
+            Orientation::Smaller
+        };
+        (picked_card, insertion_idx, orientation)
+    }
+
+    /// Whether each index of the space is a legal move for the player to act
+    pub fn legal_action_mask(
+        &self,
+        public_state: &PublicState,
+        hidden_state: &PlayerHiddenState,
+    ) -> Vec<bool> {
+        let mut mask = vec![false; self.size()];
+        self.legal_action_mask_into(public_state, hidden_state, &mut mask);
+        mask
+    }
+
+    /// As legal_action_mask, writing into mask which must have size() values
+    pub fn legal_action_mask_into(
+        &self,
+        public_state: &PublicState,
+        hidden_state: &PlayerHiddenState,
+        mask: &mut [bool],
+    ) {
+        assert_eq!(self.size(), mask.len());
+        mask.fill(false);
+        for action in MoveIter::new(public_state, hidden_state) {
+            let idx = action
+                .to_index(self)
+                .expect("Legal move outside of the action space");
+            mask[idx] = true;
+        }
+    }
+}
+
+/// Legal action mask over the action space of the game the state belongs to, see
+/// ActionSpace::legal_action_mask
+pub fn legal_action_mask(
+    public_state: &PublicState,
+    hidden_state: &PlayerHiddenState,
+) -> Vec<bool> {
+    ActionSpace::from_public_state(public_state).legal_action_mask(public_state, hidden_state)
+}
+
+impl Action {
+    /// Index of the action in the space, None when its indices do not fit the space
+    pub fn to_index(&self, space: &ActionSpace) -> Option<usize> {
+        match self {
+            Action::ChooseOrientation(FlipHand::DoFlip) => Some(0),
+            Action::ChooseOrientation(FlipHand::DoNotFlip) => Some(1),
+            Action::PlayCards(start_idx, end_idx) => space
+                .play_index((*start_idx, *end_idx))
+                .map(|idx| space.play_offset() + idx),
+            Action::PlayScoutToken(scout) => space
+                .scout_index(scout)
+                .map(|idx| space.scout_offset() + idx),
+            Action::ScoutAndShow(scout, play) => {
+                let scout_idx = space.scout_index(scout)?;
+                let play_idx = space.play_index(*play)?;
+                Some(space.scout_and_show_offset() + scout_idx * space.num_plays() + play_idx)
+            }
+        }
+    }
+
+    /// The action with the index, None when the index is outside the space. Scout & Show moves
+    /// that end in the same position as another one still have an index and decode, but are
+    /// never set in legal_action_mask, see MoveIter.
+    pub fn from_index(space: &ActionSpace, idx: usize) -> Option<Action> {
+        if idx < space.play_offset() {
+            Some(Action::ChooseOrientation(if idx == 0 {
+                FlipHand::DoFlip
+            } else {
+                FlipHand::DoNotFlip
+            }))
+        } else if idx < space.scout_offset() {
+            let (start_idx, end_idx) = space.play_from_index(idx - space.play_offset());
+            Some(Action::PlayCards(start_idx, end_idx))
+        } else if idx < space.scout_and_show_offset() {
+            Some(Action::PlayScoutToken(
+                space.scout_from_index(idx - space.scout_offset()),
+            ))
+        } else if idx < space.size() {
+            let idx = idx - space.scout_and_show_offset();
+            Some(Action::ScoutAndShow(
+                space.scout_from_index(idx / space.num_plays()),
+                space.play_from_index(idx % space.num_plays()),
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::seq::IteratorRandom;
+    use rand::SeedableRng;
+    use rand_xoshiro::SplitMix64;
+
+    #[test]
+    fn test_index_round_trip() {
+        let space = ActionSpace::new(&GameConfig::new(2, 6, 1));
+        // 6 cards dealt, a hand can hold 5
+        assert_eq!(5, space.max_hand_len);
+        assert_eq!(2 + 15 + 20 + 20 * 15, space.size());
+        for idx in 0..space.size() {
+            let action = Action::from_index(&space, idx).unwrap();
+            assert_eq!(Some(idx), action.to_index(&space), "{:?}", action);
+        }
+        assert_eq!(None, Action::from_index(&space, space.size()));
+        assert_eq!(None, Action::PlayCards(2, 6).to_index(&space));
+        // A hand of 11 gains at most the 3 tokens and a card held during Scout & Show
+        assert_eq!(
+            15,
+            ActionSpace::new(&GameConfig::new(2, 10, 3)).max_hand_len
+        );
+        // Scouting is unlimited, every card but one per other player
+        let config = GameConfig::published(5).unwrap();
+        assert_eq!(41, ActionSpace::new(&config).max_hand_len);
+        assert_eq!(None, Action::PlayCards(2, 2).to_index(&space));
+        assert_eq!(Some(2), Action::PlayCards(0, 1).to_index(&space));
+        assert_eq!(
+            Some(2 + 15),
+            Action::PlayScoutToken((PickedCard::FirstCard, 0, Orientation::Larger))
+                .to_index(&space)
+        );
+    }
+
+    #[test]
+    fn test_legal_action_mask() {
+        let configs = [
+            GameConfig::new(2, 10, 3),
+            GameConfig::new(3, 8, 1),
+            GameConfig::published(5).unwrap(),
+        ];
+        let mut rng = SplitMix64::seed_from_u64(5);
+        for config in &configs {
+            let space = ActionSpace::new(config);
+            for seed in 0..3 {
+                let mut state = GameState::new(config, seed).unwrap();
+                while !state.public_state.game_complete {
+                    assert_eq!(space, ActionSpace::from_public_state(&state.public_state));
+                    let mask = legal_action_mask(&state.public_state, state.current_hidden_state());
+                    let moves: Vec<Action> =
+                        MoveIter::new(&state.public_state, state.current_hidden_state()).collect();
+                    assert_eq!(moves.len(), mask.iter().filter(|&&legal| legal).count());
+
+                    let idx = (0..space.size())
+                        .filter(|&idx| mask[idx])
+                        .choose(&mut rng)
+                        .unwrap();
+                    let action = Action::from_index(&space, idx).unwrap();
+                    assert!(moves.contains(&action));
+                    state.transition(&action);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_observation() {
+        let config = GameConfig::new(3, 8, 1);
+        let encoder = ObservationEncoder::new(&config, 4);
+        let mut state = GameState::new(&config, 3).unwrap();
+        for action in [
+            Action::ChooseOrientation(FlipHand::DoFlip),
+            Action::ChooseOrientation(FlipHand::DoNotFlip),
+            Action::ChooseOrientation(FlipHand::DoNotFlip),
+        ] {
+            state.transition(&action);
+        }
+        let play = MoveIter::new(&state.public_state, state.current_hidden_state())
+            .next()
+            .unwrap();
+        state.transition(&play);
+
+        let observer = 1;
+        let encoded = encoder.encode(
+            &state.public_state,
+            &state.hidden_states[observer],
+            observer,
+        );
+        assert_eq!(encoder.size(), encoded.len());
+        assert_eq!(OBSERVATION_VERSION as f32, encoded[0]);
+        assert_eq!([1.0, 0.0], encoded[1..3]);
+        // The first hand card, top then bottom one-hots
+        let first_card = state.hidden_states[observer].hand[0];
+        assert_eq!(1.0, encoded[3 + first_card.top() as usize - 1]);
+        assert_eq!(1.0, encoded[3 + 8 + first_card.bottom() as usize - 1]);
+        assert_eq!(2.0, encoded[3..3 + 16].iter().sum::<f32>());
+        // The observer is to act, seat 2 owns the board
+        let seats = 3 + 2 * encoder.hand_size();
+        assert_eq!(1.0, encoded[seats + 5]);
+        assert_eq!(1.0, encoded[seats + 2 * SEAT_SIZE + 4]);
+        // The latest action was by seat 2, the other three history entries were orientations
+        let history = seats + 3 * SEAT_SIZE;
+        assert_eq!([1.0, 2.0, 0.0, 1.0], encoded[history..history + 4]);
+        assert_eq!(
+            4.0,
+            encoded[history..]
+                .iter()
+                .step_by(HISTORY_ENTRY_SIZE)
+                .sum::<f32>()
+        );
+
+        // Only the observation, other hands do not change it
+        let mut swapped = state.clone();
+        swapped.hidden_states.swap(0, 2);
+        assert_eq!(
+            encoded,
+            encoder.encode(
+                &swapped.public_state,
+                &swapped.hidden_states[observer],
+                observer
+            )
+        );
+        let perfect = encoder.encode_perfect_information(&state, observer);
+        assert_eq!(encoder.perfect_information_size(), perfect.len());
+        assert_eq!(encoded, perfect[..encoder.size()]);
+        assert_ne!(
+            perfect,
+            encoder.encode_perfect_information(&swapped, observer)
+        );
+    }
+
+    #[test]
+    fn test_scout_and_show_history_entry() {
+        let config = GameConfig::new(2, 8, 1);
+        let encoder = ObservationEncoder::new(&config, 1);
+        let mut state = GameState::new(&config, 3).unwrap();
+        for _ in 0..2 {
+            state.transition(&Action::ChooseOrientation(FlipHand::DoNotFlip));
+        }
+        state.transition(&Action::PlayCards(0, 1));
+        let scout_and_show = MoveIter::new(&state.public_state, state.current_hidden_state())
+            .find(|action| matches!(action, Action::ScoutAndShow((_, 2, _), _)))
+            .unwrap();
+        let Action::ScoutAndShow(_, (start_idx, end_idx)) = scout_and_show else {
+            unreachable!();
+        };
+        state.transition(&scout_and_show);
+
+        let encoded = encoder.encode(&state.public_state, &state.hidden_states[0], 0);
+        let entry = &encoded[encoder.size() - HISTORY_ENTRY_SIZE..];
+        assert_eq!(1.0, entry[2 + 3]);
+        assert_eq!([2.0, start_idx as f32, end_idx as f32], entry[7..10]);
+    }
+}
@@ -5,6 +5,8 @@ use rand::seq::SliceRandom;
 use rand::{Rng, SeedableRng};
 use rand_xoshiro::SplitMix64;
 
+use crate::config::{ConfigError, GameConfig};
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Card {
     first: u8,
@@ -17,7 +19,7 @@ impl fmt::Display for Card {
             if n == 10 {
                 'T'
             } else {
-                (n + ('0' as u8)) as char
+                (n + b'0') as char
             }
         };
         write!(f, "{}|{}", to_char(self.first), to_char(self.second))
@@ -68,35 +70,15 @@ impl PartialOrd for CardSet {
     }
 }
 
-fn build_deck(max_num: u8) -> Vec<Card> {
-    // e.g. 10 * 9 / 2 = 45, but -1 so it is divisible by 4 (two games with 10 cards per player)
-    // so with 3 it is: 3 * 2 / 2. but that is only 3 cards, so for two games that means each player
-    // doesn't get a card. we need at least a max_num of 4 to give each player a single card.
-    debug_assert!(max_num >= 4);
-    let total_cards = max_num * (max_num - 1) / 2 - (max_num * (max_num - 1) / 2 % 4);
-
-    let mut deck = Vec::with_capacity(total_cards as usize);
-    let mut count = 0;
-    'outer: for i in 1..max_num {
-        for j in i + 1..=max_num {
-            deck.push(Card {
-                first: i,
-                second: j,
-            });
-            count += 1;
-            if count == total_cards {
-                break 'outer;
-            }
-        }
-    }
-
-    debug_assert_eq!(deck.len(), total_cards as usize);
-    debug_assert_eq!(deck.len() % 4, 0usize);
-
-    deck
+fn build_deck(config: &GameConfig) -> Vec<Card> {
+    config
+        .cards()
+        .into_iter()
+        .map(|(first, second)| Card { first, second })
+        .collect()
 }
 
-fn shuffle_deck(deck: &mut Vec<Card>, seed: u64) -> Vec<OrientedCard> {
+fn shuffle_deck(deck: &mut [Card], seed: u64) -> Vec<OrientedCard> {
     // We want this to be reproducable, so use SplitMix64 specifically
     let mut rng = SplitMix64::seed_from_u64(seed);
     deck.shuffle(&mut rng);
@@ -126,14 +108,32 @@ pub struct OrientedCard {
 }
 
 impl OrientedCard {
-    pub fn top(self: &Self) -> u8 {
+    /// The card with numbers (first, second), first smaller than second
+    pub fn new((first, second): (u8, u8), orientation: Orientation) -> Self {
+        debug_assert!(first < second);
+        OrientedCard {
+            card: Card { first, second },
+            orientation,
+        }
+    }
+
+    /// The card's numbers, smaller first, whatever the orientation
+    pub fn numbers(&self) -> (u8, u8) {
+        (self.card.first, self.card.second)
+    }
+
+    pub fn orientation(&self) -> Orientation {
+        self.orientation
+    }
+
+    pub fn top(&self) -> u8 {
         match self.orientation {
             Orientation::Smaller => self.card.first,
             Orientation::Larger => self.card.second,
         }
     }
 
-    pub fn bottom(self: &Self) -> u8 {
+    pub fn bottom(&self) -> u8 {
         match self.orientation {
             Orientation::Smaller => self.card.second,
             Orientation::Larger => self.card.first,
@@ -158,13 +158,58 @@ impl fmt::Display for OrientedCard {
             if n == 10 {
                 'T'
             } else {
-                (n + ('0' as u8)) as char
+                (n + b'0') as char
             }
         };
         write!(f, "{}({})", to_char(self.top()), to_char(self.bottom()))
     }
 }
 
+/// Seat index, turn order goes up from the round's first player and wraps around
+pub type PlayerId = usize;
+
+/// How scout tokens are obtained and spent
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ScoutTokenRule {
+    /// Each player starts with a limited number of tokens and spends one to scout.
+    /// Scouting does not end the turn, unspent tokens score a point each.
+    #[default]
+    Spend,
+    /// Published rules: scouting is unlimited and ends the turn, and the owner of the
+    /// scouted set receives a token worth a point.
+    AwardOwner,
+}
+
+/// When the round ends, besides a player emptying their hand
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum RoundEndRule {
+    /// The round ends when the player to move has no legal move
+    #[default]
+    Stuck,
+    /// Published rules: the round also ends when every other player scouted and play comes
+    /// back to the owner of the board set. The owner's hand is not counted.
+    ReturnToOwner,
+}
+
+/// Rule variants, all defaults reproduce the original two player variant
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Rules {
+    pub scout_tokens: ScoutTokenRule,
+    pub round_end: RoundEndRule,
+}
+
+impl Rules {
+    /// The rules as published for the retail game
+    pub fn published() -> Self {
+        Rules {
+            scout_tokens: ScoutTokenRule::AwardOwner,
+            round_end: RoundEndRule::ReturnToOwner,
+        }
+    }
+}
+
+pub const MAX_PLAYERS: usize = 5;
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct PlayerHiddenState {
     pub hand: Vec<OrientedCard>,
@@ -172,22 +217,45 @@ pub struct PlayerHiddenState {
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct PublicState {
+    pub rules: Rules,
+
     pub game_complete: bool,
     pub orientation_chosen: bool,
-    pub is_player_one_turn: bool,
+    /// Chooses orientation first and makes the first play
+    pub first_player: PlayerId,
+    pub current_player: PlayerId,
 
     pub board: Vec<OrientedCard>,
+    /// The player who played the current board set, None when the board is empty
+    pub board_owner: Option<PlayerId>,
+
+    // Per seat, indexed by PlayerId
+    pub card_counts: Vec<u8>,
+    pub scout_token_counts: Vec<u8>,
+    pub won_cards: Vec<u8>,
+    pub scout_and_show_used: Vec<bool>,
+    /// A slot per card in hand, the cards every player knows to be there. Those are the cards
+    /// picked up by scouting, the rest of a hand was dealt face down.
+    pub known_cards: Vec<Vec<Option<OrientedCard>>>,
+    /// Every set shown on the board this round, in order
+    pub shown_sets: Vec<Vec<OrientedCard>>,
+
+    /// (player who acted, action, result)
+    pub action_history: Vec<(PlayerId, Action, TransitionResult)>,
+}
 
-    pub player_one_card_count: u8,
-    pub player_two_card_count: u8,
-
-    pub player_one_scout_token_count: u8,
-    pub player_two_scout_token_count: u8,
-
-    pub player_one_won_cards: u8,
-    pub player_two_won_cards: u8,
+impl PublicState {
+    pub fn num_players(&self) -> usize {
+        self.card_counts.len()
+    }
 
-    pub action_history: Vec<(bool, Action, TransitionResult)>,
+    /// Whether the rules allow the player to scout, ignoring the state of the board
+    pub fn can_scout(&self, player: PlayerId) -> bool {
+        match self.rules.scout_tokens {
+            ScoutTokenRule::Spend => self.scout_token_counts[player] > 0,
+            ScoutTokenRule::AwardOwner => true,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -212,6 +280,9 @@ pub enum Action {
     PlayCards(u8, u8),
     // First or last card -> (index, orientation)
     PlayScoutToken((PickedCard, u8, Orientation)),
+    // Scout as with PlayScoutToken, then play (start, end) from the resulting hand.
+    // Uses the once per round Scout & Show chip rather than a scout token.
+    ScoutAndShow((PickedCard, u8, Orientation), (u8, u8)),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -219,25 +290,29 @@ pub enum IllegalMoveReason {
     GameComplete,
     BadHandIndex,
     MustChooseOrientation,
+    OrientationChosen,
     DoesNotBeatBoard,
     InvalidSet,
     NoScoutTokens,
     ScoutWhenBoardEmpty,
+    ScoutAndShowUsed,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum TransitionResult {
     // Transition did occur, game state was updated
     MoveAccepted,
-    GameComplete(i8, i8),
+    // Score per player, indexed by PlayerId
+    GameComplete(Vec<i8>),
 
     // Transition did not occur, game state unchanged
     IllegalMove(IllegalMoveReason),
 }
 
-fn build_card_set(to_play: &[OrientedCard]) -> Option<CardSet> {
+/// The set the cards form in order, None if they do not form one
+pub fn build_card_set(to_play: &[OrientedCard]) -> Option<CardSet> {
     let vals: Vec<u8> = to_play.iter().map(|c| c.top()).collect();
-    if vals.len() == 0 {
+    if vals.is_empty() {
         return None;
     }
 
@@ -264,11 +339,41 @@ fn build_card_set(to_play: &[OrientedCard]) -> Option<CardSet> {
     let first = vals[0];
     let last = *vals.last().unwrap();
     if ascending {
-        return Some(CardSet::Consecutive(first, last));
+        Some(CardSet::Consecutive(first, last))
     } else {
-        return Some(CardSet::Consecutive(last, first));
+        Some(CardSet::Consecutive(last, first))
     }
 }
+/// Returns the hand and board after moving the picked card from the board into the hand.
+/// Does not check whether the player is allowed to scout.
+pub fn apply_scout(
+    hand: &[OrientedCard],
+    board: &[OrientedCard],
+    picked_card_info: &(PickedCard, u8, Orientation),
+) -> Result<(Vec<OrientedCard>, Vec<OrientedCard>), IllegalMoveReason> {
+    let (picked_card, insertion_index, orientation) = picked_card_info;
+    if *insertion_index as usize > hand.len() {
+        return Err(IllegalMoveReason::BadHandIndex);
+    } else if board.is_empty() {
+        return Err(IllegalMoveReason::ScoutWhenBoardEmpty);
+    }
+
+    let mut new_board = board.to_vec();
+    let oriented_card = match picked_card {
+        PickedCard::FirstCard => new_board.remove(0),
+        PickedCard::LastCard => new_board.pop().unwrap(),
+    };
+    let mut new_hand = hand.to_vec();
+    new_hand.insert(
+        *insertion_index as usize,
+        OrientedCard {
+            card: oriented_card.card,
+            orientation: *orientation,
+        },
+    );
+    Ok((new_hand, new_board))
+}
+
 /// Illegal set is checked before checking if the proposed play beats the board.
 pub fn legal_and_beats_board(
     board: &[OrientedCard],
@@ -287,134 +392,218 @@ pub fn legal_and_beats_board(
     }
 }
 
+/// Mixes a position feature into a pseudo random 64 bit key, the SplitMix64 finalizer
+fn zobrist(feature: u64) -> u64 {
+    let mut z = feature.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+// Feature kinds of the position key, packed into the top byte of a feature
+const ZOBRIST_HAND_CARD: u64 = 1;
+const ZOBRIST_BOARD_CARD: u64 = 2;
+const ZOBRIST_CARD_COUNT: u64 = 3;
+const ZOBRIST_SCOUT_TOKENS: u64 = 4;
+const ZOBRIST_WON_CARDS: u64 = 5;
+const ZOBRIST_SCOUT_AND_SHOW_USED: u64 = 6;
+const ZOBRIST_CURRENT_PLAYER: u64 = 7;
+const ZOBRIST_BOARD_OWNER: u64 = 8;
+const ZOBRIST_ORIENTATION_CHOSEN: u64 = 9;
+const ZOBRIST_GAME_COMPLETE: u64 = 10;
+
+fn zobrist_feature(kind: u64, player: usize, index: usize, value: u64) -> u64 {
+    zobrist(kind << 56 | (player as u64) << 48 | (index as u64) << 32 | value)
+}
+
+fn zobrist_card(kind: u64, player: usize, index: usize, card: &OrientedCard) -> u64 {
+    let orientation = match card.orientation {
+        Orientation::Larger => 0,
+        Orientation::Smaller => 1,
+    };
+    let value = (card.card.first as u64) << 16 | (card.card.second as u64) << 8 | orientation;
+    zobrist_feature(kind, player, index, value)
+}
+
+/// Restores a GameState to how it was before an accepted transition, see
+/// GameState::transition_with_undo. The rest is recovered from the action history.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Undo {
+    position_key: u64,
+    board: Vec<OrientedCard>,
+    board_owner: Option<PlayerId>,
+    board_owner_tokens: u8,
+    current_player: PlayerId,
+    orientation_chosen: bool,
+    actor_card_count: u8,
+    actor_scout_tokens: u8,
+    actor_won_cards: u8,
+    actor_scout_and_show_used: bool,
+    actor_known_cards: Vec<Option<OrientedCard>>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct GameState {
     pub seed: u64,
     pub public_state: PublicState,
-    pub player_one_hidden_state: PlayerHiddenState,
-    pub player_two_hidden_state: PlayerHiddenState,
+    /// Indexed by PlayerId
+    pub hidden_states: Vec<PlayerHiddenState>,
+    /// See position_key, kept up to date by transition and undo
+    position_key: u64,
 }
 
 impl GameState {
-    pub fn new(max_card_num: u8, scout_tokens: u8, seed: u64) -> Self {
-        // If max_card_num is too high then u8 could overflow
-        // 40 is an abritrary limit, the game itself plays up to 10
-        debug_assert!(max_card_num < 40);
+    pub fn new(config: &GameConfig, seed: u64) -> Result<Self, ConfigError> {
+        config.validate()?;
 
-        let mut deck = build_deck(max_card_num);
+        let mut deck = build_deck(config);
         let shuffled_deck = shuffle_deck(&mut deck, seed);
+        let hands = shuffled_deck
+            .chunks_exact(config.hand_size)
+            .take(config.num_players)
+            .map(|hand| hand.to_vec())
+            .collect();
+        Self::from_hands(config, hands, seed)
+    }
 
-        let cards_per_player = deck.len() / 4;
-
-        let player_one_hidden_state = PlayerHiddenState {
-            hand: shuffled_deck[0..cards_per_player].to_vec(),
-        };
-        let player_two_hidden_state = PlayerHiddenState {
-            hand: shuffled_deck[cards_per_player..cards_per_player * 2].to_vec(),
-        };
-
-        debug_assert_eq!(player_one_hidden_state.hand.len(), cards_per_player);
-        debug_assert_eq!(player_two_hidden_state.hand.len(), cards_per_player);
+    /// A round dealt with the given hands, one per player of config.hand_size cards each.
+    /// The seed is only recorded.
+    pub fn from_hands(
+        config: &GameConfig,
+        hands: Vec<Vec<OrientedCard>>,
+        seed: u64,
+    ) -> Result<Self, ConfigError> {
+        config.validate()?;
+        let num_players = config.num_players;
+        assert_eq!(num_players, hands.len());
+        assert!(hands.iter().all(|hand| hand.len() == config.hand_size));
+        let hidden_states: Vec<PlayerHiddenState> = hands
+            .into_iter()
+            .map(|hand| PlayerHiddenState { hand })
+            .collect();
 
         let public_state = PublicState {
+            rules: config.rules,
             game_complete: false,
             orientation_chosen: false,
-            is_player_one_turn: true,
+            first_player: config.first_player,
+            current_player: config.first_player,
             board: vec![],
-            player_one_card_count: cards_per_player as u8,
-            player_two_card_count: cards_per_player as u8,
-
-            player_one_won_cards: 0,
-            player_two_won_cards: 0,
-
-            player_one_scout_token_count: scout_tokens,
-            player_two_scout_token_count: scout_tokens,
+            board_owner: None,
+            card_counts: vec![config.hand_size as u8; num_players],
+            won_cards: vec![0; num_players],
+            scout_token_counts: vec![config.scout_tokens; num_players],
+            scout_and_show_used: vec![false; num_players],
+            known_cards: vec![vec![None; config.hand_size]; num_players],
+            shown_sets: vec![],
 
             action_history: vec![],
         };
 
-        GameState {
+        let mut state = GameState {
             seed,
             public_state,
-            player_one_hidden_state,
-            player_two_hidden_state,
+            hidden_states,
+            position_key: 0,
+        };
+        state.refresh_position_key();
+        Ok(state)
+    }
+
+    pub fn num_players(&self) -> usize {
+        self.public_state.num_players()
+    }
+
+    /// The scores of GameComplete, None until the round is complete
+    pub fn final_scores(&self) -> Option<&[i8]> {
+        match self.public_state.action_history.last() {
+            Some((_, _, TransitionResult::GameComplete(scores))) => Some(scores),
+            _ => None,
         }
     }
 
+    /// The hidden state of the player whose turn it is
+    pub fn current_hidden_state(&self) -> &PlayerHiddenState {
+        &self.hidden_states[self.public_state.current_player]
+    }
+
+    fn next_player(&self) -> PlayerId {
+        (self.public_state.current_player + 1) % self.num_players()
+    }
+
     fn handle_orientation_action(&mut self, do_flip: &FlipHand) -> TransitionResult {
-        if self.public_state.is_player_one_turn {
-            match *do_flip {
-                FlipHand::DoFlip => {
-                    self.player_one_hidden_state.hand = self
-                        .player_one_hidden_state
-                        .hand
-                        .iter()
-                        .map(|c| c.flip())
-                        .collect();
-                }
-                FlipHand::DoNotFlip => {}
-            }
-            self.public_state.is_player_one_turn = false;
-        } else {
-            match *do_flip {
-                FlipHand::DoFlip => {
-                    self.player_two_hidden_state.hand = self
-                        .player_two_hidden_state
-                        .hand
-                        .iter()
-                        .map(|c| c.flip())
-                        .collect();
+        if self.public_state.orientation_chosen {
+            return TransitionResult::IllegalMove(IllegalMoveReason::OrientationChosen);
+        }
+        let player = self.public_state.current_player;
+        match *do_flip {
+            FlipHand::DoFlip => {
+                self.hidden_states[player].hand = self.hidden_states[player]
+                    .hand
+                    .iter()
+                    .map(|c| c.flip())
+                    .collect();
+                for known_card in self.public_state.known_cards[player].iter_mut().flatten() {
+                    *known_card = known_card.flip();
                 }
-                FlipHand::DoNotFlip => {}
             }
-            self.public_state.is_player_one_turn = true;
+            FlipHand::DoNotFlip => {}
+        }
+        self.public_state.current_player = self.next_player();
+        if self.public_state.current_player == self.public_state.first_player {
             self.public_state.orientation_chosen = true;
         }
         TransitionResult::MoveAccepted
     }
 
     fn accept_or_complete(&self) -> TransitionResult {
-        if self.public_state.player_one_card_count == 0 {
-            self.build_game_complete(true)
-        } else if self.public_state.player_two_card_count == 0 {
-            self.build_game_complete(false)
-        } else if self.public_state.is_player_one_turn
-            && self.public_state.player_one_scout_token_count == 0
-            && !self.has_legal_play(true)
-        {
-            self.build_game_complete(false)
-        } else if !self.public_state.is_player_one_turn
-            && self.public_state.player_two_scout_token_count == 0
-            && !self.has_legal_play(false)
+        if let Some(player) = self.public_state.card_counts.iter().position(|&c| c == 0) {
+            self.build_game_complete(player)
+        } else if self.public_state.rules.round_end == RoundEndRule::ReturnToOwner
+            && self.public_state.board_owner == Some(self.public_state.current_player)
         {
-            self.build_game_complete(true)
+            self.build_game_complete(self.public_state.current_player)
+        } else if !self.has_legal_move(self.public_state.current_player) {
+            // The player to move is stuck, which can only happen against a non-empty board.
+            // The hand of the player who played that board is not counted.
+            self.build_game_complete(self.public_state.board_owner.unwrap())
         } else {
             TransitionResult::MoveAccepted
         }
     }
 
-    fn build_game_complete(&self, player_one_scores: bool) -> TransitionResult {
-        if player_one_scores {
-            TransitionResult::GameComplete(
-                self.public_state.player_one_won_cards as i8
-                    + self.public_state.player_one_scout_token_count as i8,
-                self.public_state.player_two_won_cards as i8
-                    - self.public_state.player_two_card_count as i8
-                    + self.public_state.player_two_scout_token_count as i8,
-            )
-        } else {
-            TransitionResult::GameComplete(
-                self.public_state.player_one_won_cards as i8
-                    - self.public_state.player_one_card_count as i8
-                    + self.public_state.player_one_scout_token_count as i8,
-                self.public_state.player_two_won_cards as i8
-                    + self.public_state.player_two_scout_token_count as i8,
-            )
-        }
+    fn has_legal_move(&self, player: PlayerId) -> bool {
+        self.public_state.can_scout(player)
+            || self.has_legal_play(player)
+            || self.has_legal_scout_and_show(player)
+    }
+
+    /// Every player scores their won cards and held tokens, and all but the exempt player
+    /// lose a point per card left in hand. Held tokens are the unspent ones with
+    /// ScoutTokenRule::Spend and the ones received with ScoutTokenRule::AwardOwner.
+    fn build_game_complete(&self, exempt_player: PlayerId) -> TransitionResult {
+        let public_state = &self.public_state;
+        TransitionResult::GameComplete(
+            (0..self.num_players())
+                .map(|player| {
+                    let in_hand = if player == exempt_player {
+                        0
+                    } else {
+                        public_state.card_counts[player] as i32
+                    };
+                    // Tokens received with ScoutTokenRule::AwardOwner are not bounded by the
+                    // config, such scores saturate
+                    let score = public_state.won_cards[player] as i32
+                        + public_state.scout_token_counts[player] as i32
+                        - in_hand;
+                    score.clamp(i8::MIN as i32, i8::MAX as i32) as i8
+                })
+                .collect(),
+        )
     }
 
     /// Handles a PlayCards action
-    fn handle_play_card_action(self: &mut Self, start_idx: &u8, end_idx: &u8) -> TransitionResult {
+    fn handle_play_card_action(&mut self, start_idx: &u8, end_idx: &u8) -> TransitionResult {
         if !self.public_state.orientation_chosen {
             return TransitionResult::IllegalMove(IllegalMoveReason::MustChooseOrientation);
         }
@@ -423,12 +612,8 @@ impl GameState {
         }
         let start_idx_u = *start_idx as usize;
         let end_idx_u = *end_idx as usize;
-        let hand;
-        if self.public_state.is_player_one_turn {
-            hand = &self.player_one_hidden_state.hand;
-        } else {
-            hand = &self.player_two_hidden_state.hand;
-        }
+        let player = self.public_state.current_player;
+        let hand = &self.hidden_states[player].hand;
         if end_idx_u > hand.len() {
             return TransitionResult::IllegalMove(IllegalMoveReason::BadHandIndex);
         }
@@ -438,35 +623,24 @@ impl GameState {
             return TransitionResult::IllegalMove(illegal_move);
         }
 
-        let board_cards = self.public_state.board.iter().map(|c| c.card);
-
-        if self.public_state.is_player_one_turn {
-            self.public_state.player_one_card_count -= proposed_play.len() as u8;
-            self.public_state.player_one_won_cards += board_cards.len() as u8;
-            self.public_state.board = proposed_play.to_vec();
-            self.player_one_hidden_state
-                .hand
-                .drain(start_idx_u..end_idx_u);
-            self.public_state.is_player_one_turn = false;
-        } else {
-            self.public_state.player_two_card_count -= proposed_play.len() as u8;
-            self.public_state.player_two_won_cards += board_cards.len() as u8;
-            self.public_state.board = proposed_play.to_vec();
-            self.player_two_hidden_state
-                .hand
-                .drain(start_idx_u..end_idx_u);
-            self.public_state.is_player_one_turn = true;
-        }
+        self.public_state.card_counts[player] -= proposed_play.len() as u8;
+        self.public_state.won_cards[player] += self.public_state.board.len() as u8;
+        self.public_state.board = proposed_play.to_vec();
+        self.public_state
+            .shown_sets
+            .push(self.public_state.board.clone());
+        self.public_state.board_owner = Some(player);
+        self.hidden_states[player]
+            .hand
+            .drain(start_idx_u..end_idx_u);
+        self.public_state.known_cards[player].drain(start_idx_u..end_idx_u);
+        self.public_state.current_player = self.next_player();
 
         self.accept_or_complete()
     }
 
-    fn has_legal_play(self: &Self, check_player_one: bool) -> bool {
-        let hand = if check_player_one {
-            &self.player_one_hidden_state.hand
-        } else {
-            &self.player_two_hidden_state.hand
-        };
+    fn has_legal_play(&self, player: PlayerId) -> bool {
+        let hand = &self.hidden_states[player].hand;
 
         (1..=hand.len()).any(|window_size| {
             hand.windows(window_size)
@@ -474,8 +648,91 @@ impl GameState {
         })
     }
 
+    fn has_legal_scout_and_show(&self, player: PlayerId) -> bool {
+        let hand = &self.hidden_states[player].hand;
+        if self.public_state.scout_and_show_used[player] || self.public_state.board.is_empty() {
+            return false;
+        }
+
+        let picked_cards = if self.public_state.board.len() > 1 {
+            vec![PickedCard::FirstCard, PickedCard::LastCard]
+        } else {
+            vec![PickedCard::FirstCard]
+        };
+        picked_cards.into_iter().any(|picked_card| {
+            (0..=hand.len() as u8).any(|insertion_index| {
+                [Orientation::Larger, Orientation::Smaller]
+                    .into_iter()
+                    .any(|orientation| {
+                        let scout = (picked_card.clone(), insertion_index, orientation);
+                        let (new_hand, new_board) =
+                            apply_scout(hand, &self.public_state.board, &scout).unwrap();
+                        (1..=new_hand.len()).any(|window_size| {
+                            new_hand
+                                .windows(window_size)
+                                .any(|window| legal_and_beats_board(&new_board, window).is_none())
+                        })
+                    })
+            })
+        })
+    }
+
+    fn handle_scout_and_show(
+        &mut self,
+        picked_card_info: &(PickedCard, u8, Orientation),
+        play: &(u8, u8),
+    ) -> TransitionResult {
+        if !self.public_state.orientation_chosen {
+            return TransitionResult::IllegalMove(IllegalMoveReason::MustChooseOrientation);
+        }
+        let player = self.public_state.current_player;
+        if self.public_state.scout_and_show_used[player] {
+            return TransitionResult::IllegalMove(IllegalMoveReason::ScoutAndShowUsed);
+        }
+
+        let (mut new_hand, new_board) = match apply_scout(
+            &self.hidden_states[player].hand,
+            &self.public_state.board,
+            picked_card_info,
+        ) {
+            Ok(scouted) => scouted,
+            Err(reason) => return TransitionResult::IllegalMove(reason),
+        };
+
+        let (start_idx, end_idx) = (play.0 as usize, play.1 as usize);
+        if start_idx >= end_idx || end_idx > new_hand.len() {
+            return TransitionResult::IllegalMove(IllegalMoveReason::BadHandIndex);
+        }
+        let proposed_play = &new_hand[start_idx..end_idx];
+        if let Some(illegal_move) = legal_and_beats_board(&new_board, proposed_play) {
+            return TransitionResult::IllegalMove(illegal_move);
+        }
+
+        let num_played = proposed_play.len() as u8;
+        if self.public_state.rules.scout_tokens == ScoutTokenRule::AwardOwner {
+            self.award_scout_token();
+        }
+        let insertion_idx = picked_card_info.1 as usize;
+        let known_cards = &mut self.public_state.known_cards[player];
+        known_cards.insert(insertion_idx, Some(new_hand[insertion_idx]));
+        known_cards.drain(start_idx..end_idx);
+        self.public_state.board = new_hand.drain(start_idx..end_idx).collect();
+        self.public_state
+            .shown_sets
+            .push(self.public_state.board.clone());
+        self.public_state.board_owner = Some(player);
+        self.public_state.card_counts[player] =
+            self.public_state.card_counts[player] + 1 - num_played;
+        self.public_state.won_cards[player] += new_board.len() as u8;
+        self.public_state.scout_and_show_used[player] = true;
+        self.hidden_states[player].hand = new_hand;
+        self.public_state.current_player = self.next_player();
+
+        self.accept_or_complete()
+    }
+
     fn handle_play_scout_token(
-        self: &mut Self,
+        &mut self,
         picked_card_info: &(PickedCard, u8, Orientation),
     ) -> TransitionResult {
         if !self.public_state.orientation_chosen {
@@ -485,59 +742,79 @@ impl GameState {
         let insertion_index = picked_card_info.1;
         let orientation = &picked_card_info.2;
 
-        let hand;
-        if self.public_state.is_player_one_turn {
-            if self.public_state.player_one_scout_token_count == 0 {
-                return TransitionResult::IllegalMove(IllegalMoveReason::NoScoutTokens);
-            }
-            hand = &mut self.player_one_hidden_state.hand;
-        } else {
-            if self.public_state.player_two_scout_token_count == 0 {
-                return TransitionResult::IllegalMove(IllegalMoveReason::NoScoutTokens);
-            }
-            hand = &mut self.player_two_hidden_state.hand;
+        let player = self.public_state.current_player;
+        if !self.public_state.can_scout(player) {
+            return TransitionResult::IllegalMove(IllegalMoveReason::NoScoutTokens);
         }
+        let hand = &mut self.hidden_states[player].hand;
 
         if insertion_index as usize > hand.len() {
             return TransitionResult::IllegalMove(IllegalMoveReason::BadHandIndex);
-        } else if self.public_state.board.len() == 0 {
+        } else if self.public_state.board.is_empty() {
             return TransitionResult::IllegalMove(IllegalMoveReason::ScoutWhenBoardEmpty);
         }
 
-        let oriented_card;
-        match picked_card {
+        let oriented_card = match picked_card {
             PickedCard::FirstCard => {
                 // remove first element of board
-                oriented_card = self.public_state.board.remove(0);
+                self.public_state.board.remove(0)
+            }
+            PickedCard::LastCard => self.public_state.board.pop().unwrap(),
+        };
+
+        let scouted_card = OrientedCard {
+            card: oriented_card.card,
+            orientation: *orientation,
+        };
+        hand.insert(insertion_index as usize, scouted_card);
+        self.public_state.known_cards[player].insert(insertion_index as usize, Some(scouted_card));
+        self.public_state.card_counts[player] += 1;
+        match self.public_state.rules.scout_tokens {
+            ScoutTokenRule::Spend => {
+                self.public_state.scout_token_counts[player] -= 1;
             }
-            PickedCard::LastCard => {
-                oriented_card = self.public_state.board.pop().unwrap();
+            ScoutTokenRule::AwardOwner => {
+                self.award_scout_token();
+                self.public_state.current_player = self.next_player();
             }
         }
-
-        hand.insert(
-            insertion_index as usize,
-            OrientedCard {
-                card: oriented_card.card,
-                orientation: *orientation,
-            },
-        );
-        if self.public_state.is_player_one_turn {
-            self.public_state.player_one_scout_token_count -= 1;
-            self.public_state.player_one_card_count += 1;
-        } else {
-            self.public_state.player_two_scout_token_count -= 1;
-            self.public_state.player_two_card_count += 1;
+        if self.public_state.board.is_empty() {
+            self.public_state.board_owner = None;
         }
         self.accept_or_complete()
     }
 
-    pub fn transition(self: &mut Self, action: &Action) -> TransitionResult {
+    /// Gives the owner of the board set a token for being scouted from
+    fn award_scout_token(&mut self) {
+        match self.public_state.board_owner {
+            Some(owner) if owner != self.public_state.current_player => {
+                let count = &mut self.public_state.scout_token_counts[owner];
+                *count = count.saturating_add(1);
+            }
+            _ => {}
+        }
+    }
+
+    pub fn transition(&mut self, action: &Action) -> TransitionResult {
         if self.public_state.game_complete {
             return TransitionResult::IllegalMove(IllegalMoveReason::GameComplete);
         }
 
-        // Three choices for the enum
+        let actor = self.public_state.current_player;
+        // Only the actor and the board owner, who may receive a token, change seats
+        let mut seats = [actor; 2];
+        let mut num_touched = 1;
+        if let Some(owner) = self
+            .public_state
+            .board_owner
+            .filter(|&owner| owner != actor)
+        {
+            seats[1] = owner;
+            num_touched = 2;
+        }
+        let touched = &seats[..num_touched];
+        let touched_key = self.partial_position_key(touched);
+
         let result = match action {
             Action::ChooseOrientation(do_flip) => self.handle_orientation_action(do_flip),
             Action::PlayCards(start_idx, end_idx) => {
@@ -546,72 +823,206 @@ impl GameState {
             Action::PlayScoutToken(picked_card_info) => {
                 self.handle_play_scout_token(picked_card_info)
             }
+            Action::ScoutAndShow(picked_card_info, play) => {
+                self.handle_scout_and_show(picked_card_info, play)
+            }
         };
 
         match result {
             TransitionResult::GameComplete(..) => {
                 self.public_state.game_complete = true;
-                self.public_state.action_history.push((
-                    self.public_state.is_player_one_turn,
-                    action.clone(),
-                    result.clone(),
-                ));
+                self.public_state
+                    .action_history
+                    .push((actor, action.clone(), result.clone()));
             }
             TransitionResult::MoveAccepted => {
-                self.public_state.action_history.push((
-                    self.public_state.is_player_one_turn,
-                    action.clone(),
-                    result.clone(),
-                ));
+                self.public_state
+                    .action_history
+                    .push((actor, action.clone(), result.clone()));
             }
-            _ => {}
+            _ => return result,
         }
 
+        self.position_key ^= touched_key ^ self.partial_position_key(touched);
         result
     }
 
+    /// As transition, but also returns what is needed to undo an accepted move. Restoring with
+    /// undo is much cheaper than cloning the whole state before each move.
+    pub fn transition_with_undo(&mut self, action: &Action) -> (TransitionResult, Option<Undo>) {
+        let public_state = &self.public_state;
+        let actor = public_state.current_player;
+        let undo = Undo {
+            position_key: self.position_key,
+            board: public_state.board.clone(),
+            board_owner: public_state.board_owner,
+            board_owner_tokens: public_state
+                .board_owner
+                .map_or(0, |owner| public_state.scout_token_counts[owner]),
+            current_player: actor,
+            orientation_chosen: public_state.orientation_chosen,
+            actor_card_count: public_state.card_counts[actor],
+            actor_scout_tokens: public_state.scout_token_counts[actor],
+            actor_won_cards: public_state.won_cards[actor],
+            actor_scout_and_show_used: public_state.scout_and_show_used[actor],
+            actor_known_cards: public_state.known_cards[actor].clone(),
+        };
+
+        let result = self.transition(action);
+        if matches!(result, TransitionResult::IllegalMove(_)) {
+            (result, None)
+        } else {
+            (result, Some(undo))
+        }
+    }
+
+    /// Reverts the last accepted transition, undo must be the one returned for it
+    pub fn undo(&mut self, undo: Undo) {
+        let (actor, action, _) = self
+            .public_state
+            .action_history
+            .pop()
+            .expect("No move to undo");
+        debug_assert_eq!(actor, undo.current_player);
+
+        let hand = &mut self.hidden_states[actor].hand;
+        let shown = &self.public_state.board;
+        match action {
+            Action::ChooseOrientation(FlipHand::DoFlip) => {
+                hand.iter_mut().for_each(|c| *c = c.flip());
+            }
+            Action::ChooseOrientation(FlipHand::DoNotFlip) => {}
+            Action::PlayCards(start_idx, _) => {
+                let start_idx = start_idx as usize;
+                hand.splice(start_idx..start_idx, shown.iter().copied());
+                self.public_state.shown_sets.pop();
+            }
+            Action::PlayScoutToken((_, insertion_idx, _)) => {
+                hand.remove(insertion_idx as usize);
+            }
+            Action::ScoutAndShow((_, insertion_idx, _), (start_idx, _)) => {
+                let start_idx = start_idx as usize;
+                hand.splice(start_idx..start_idx, shown.iter().copied());
+                hand.remove(insertion_idx as usize);
+                self.public_state.shown_sets.pop();
+            }
+        }
+
+        let public_state = &mut self.public_state;
+        public_state.board = undo.board;
+        public_state.board_owner = undo.board_owner;
+        if let Some(owner) = undo.board_owner {
+            public_state.scout_token_counts[owner] = undo.board_owner_tokens;
+        }
+        public_state.current_player = actor;
+        public_state.orientation_chosen = undo.orientation_chosen;
+        public_state.game_complete = false;
+        public_state.card_counts[actor] = undo.actor_card_count;
+        public_state.scout_token_counts[actor] = undo.actor_scout_tokens;
+        public_state.won_cards[actor] = undo.actor_won_cards;
+        public_state.scout_and_show_used[actor] = undo.actor_scout_and_show_used;
+        public_state.known_cards[actor] = undo.actor_known_cards;
+        self.position_key = undo.position_key;
+    }
+
+    /// A key for the position, usable for transposition tables. It covers the hands, board,
+    /// per seat counts, tokens and flags, the board owner and the player to move, but not the
+    /// seed or the action history, so transpositions share a key. The rules and first player
+    /// are fixed for a round and are left out.
+    pub fn position_key(&self) -> u64 {
+        self.position_key
+    }
+
+    /// Computes the position key from scratch
+    pub fn compute_position_key(&self) -> u64 {
+        let players: Vec<PlayerId> = (0..self.num_players()).collect();
+        self.partial_position_key(&players)
+    }
+
+    /// Recomputes the position key, needed after modifying the public fields directly
+    pub fn refresh_position_key(&mut self) {
+        self.position_key = self.compute_position_key();
+    }
+
+    /// Key of the features of the given seats and the shared features. A transition only
+    /// changes the features of some seats, so the key is updated by removing this partial key
+    /// from before the move and adding the one from after.
+    fn partial_position_key(&self, players: &[PlayerId]) -> u64 {
+        let public_state = &self.public_state;
+        let mut key = 0;
+        for &player in players {
+            for (idx, card) in self.hidden_states[player].hand.iter().enumerate() {
+                key ^= zobrist_card(ZOBRIST_HAND_CARD, player, idx, card);
+            }
+            let counts = [
+                (ZOBRIST_CARD_COUNT, public_state.card_counts[player]),
+                (
+                    ZOBRIST_SCOUT_TOKENS,
+                    public_state.scout_token_counts[player],
+                ),
+                (ZOBRIST_WON_CARDS, public_state.won_cards[player]),
+                (
+                    ZOBRIST_SCOUT_AND_SHOW_USED,
+                    public_state.scout_and_show_used[player] as u8,
+                ),
+            ];
+            for (kind, value) in counts {
+                key ^= zobrist_feature(kind, player, 0, value as u64);
+            }
+        }
+
+        for (idx, card) in public_state.board.iter().enumerate() {
+            key ^= zobrist_card(ZOBRIST_BOARD_CARD, 0, idx, card);
+        }
+        key ^= zobrist_feature(ZOBRIST_CURRENT_PLAYER, public_state.current_player, 0, 0);
+        if let Some(owner) = public_state.board_owner {
+            key ^= zobrist_feature(ZOBRIST_BOARD_OWNER, owner, 0, 0);
+        }
+        if public_state.orientation_chosen {
+            key ^= zobrist_feature(ZOBRIST_ORIENTATION_CHOSEN, 0, 0, 0);
+        }
+        if public_state.game_complete {
+            key ^= zobrist_feature(ZOBRIST_GAME_COMPLETE, 0, 0, 0);
+        }
+        key
+    }
+
+    /// Hash of the whole state including the seed and action history, see position_key for a
+    /// key that is shared by transpositions
     pub fn calculate_hash(&self) -> u64 {
         let mut hasher = DefaultHasher::new();
         self.hash(&mut hasher);
         hasher.finish()
     }
 
-    pub fn display(&self) -> () {
+    pub fn display(&self) {
         let hash = self.calculate_hash();
         println!("## State Hash: {:?}", hash);
         if !self.public_state.orientation_chosen {
-            if self.public_state.is_player_one_turn {
-                println!("player_one choosing hand orientation");
-            } else {
-                println!("player_two choosing hand orientation");
-            }
+            println!(
+                "player {} choosing hand orientation",
+                self.public_state.current_player
+            );
             return;
         }
 
         if self.public_state.game_complete {
             println!("--Game Complete--");
         } else {
-            print!("--Turn: ");
-            if self.public_state.is_player_one_turn {
-                println!("player_one--");
-            } else {
-                println!("player_two--");
-            }
+            println!("--Turn: player {}--", self.public_state.current_player);
         }
 
-        print!(
-            "Player One: [Tokens {:?}] [Won {:?}] [Hand: ",
-            self.public_state.player_one_scout_token_count, self.public_state.player_one_won_cards
-        );
-        print_cards(&self.player_one_hidden_state.hand);
-        println!("]");
-
-        print!(
-            "Player Two: [Tokens {:?}] [Won {:?}] [Hand: ",
-            self.public_state.player_two_scout_token_count, self.public_state.player_two_won_cards
-        );
-        print_cards(&self.player_two_hidden_state.hand);
-        println!("]");
+        for (player, hidden_state) in self.hidden_states.iter().enumerate() {
+            print!(
+                "Player {}: [Tokens {:?}] [Won {:?}] [S&S Used {:?}] [Hand: ",
+                player,
+                self.public_state.scout_token_counts[player],
+                self.public_state.won_cards[player],
+                self.public_state.scout_and_show_used[player]
+            );
+            print_cards(&hidden_state.hand);
+            println!("]");
+        }
 
         print!("Board: ");
         for card in &self.public_state.board {
@@ -621,10 +1032,11 @@ impl GameState {
                 print!(" {}", card);
             }
         }
-        println!("");
+        println!();
     }
 }
 
+#[cfg(test)]
 impl GameState {
     fn play_and_display(&mut self, action: &Action, ensure_legal: bool) -> TransitionResult {
         let result = self.transition(action);
@@ -643,6 +1055,7 @@ impl GameState {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::GameConfig;
 
     #[test]
     fn test_card_values() {
@@ -657,16 +1070,140 @@ mod tests {
 
     #[test]
     fn test_build_deck() {
-        let deck = build_deck(4);
+        let deck = build_deck(&GameConfig::new(2, 4, 0));
         assert_eq!(4, deck.len());
 
-        let deck = build_deck(10);
+        let deck = build_deck(&GameConfig::new(2, 10, 0));
         assert_eq!(44, deck.len());
     }
 
+    #[test]
+    fn test_new_from_config() {
+        let state = GameState::new(&GameConfig::published(5).unwrap(), 1).unwrap();
+        assert_eq!(vec![9; 5], state.public_state.card_counts);
+        let mut dealt: Vec<Card> = state
+            .hidden_states
+            .iter()
+            .flat_map(|hidden_state| hidden_state.hand.iter().map(|c| c.card))
+            .collect();
+        dealt.sort_by_key(|c| (c.first, c.second));
+        dealt.dedup();
+        assert_eq!(45, dealt.len());
+
+        let config = GameConfig {
+            hand_size: 30,
+            ..GameConfig::new(2, 10, 3)
+        };
+        assert_eq!(
+            Err(ConfigError::NotEnoughCards {
+                needed: 60,
+                available: 44
+            }),
+            GameState::new(&config, 1)
+        );
+    }
+
+    #[test]
+    fn test_undo_random_games() {
+        use crate::search::MoveIter;
+        use rand::seq::IteratorRandom;
+
+        let configs = [
+            GameConfig::new(2, 10, 3),
+            GameConfig::new(4, 10, 1),
+            GameConfig::published(3).unwrap(),
+            GameConfig::published(5).unwrap(),
+        ];
+        let mut rng = SplitMix64::seed_from_u64(42);
+        for config in &configs {
+            for seed in 0..20 {
+                let mut state = GameState::new(config, seed).unwrap();
+                let mut history = vec![];
+                while !state.public_state.game_complete {
+                    let action = MoveIter::new(&state.public_state, state.current_hidden_state())
+                        .choose(&mut rng)
+                        .unwrap();
+                    let before = state.clone();
+                    let (result, undo) = state.transition_with_undo(&action);
+                    assert!(!matches!(result, TransitionResult::IllegalMove(_)));
+                    assert_eq!(state.compute_position_key(), state.position_key());
+                    history.push((before, undo.unwrap()));
+                }
+
+                // An illegal move has nothing to undo
+                let (_, undo) = state.transition_with_undo(&Action::PlayCards(0, 1));
+                assert_eq!(None, undo);
+
+                while let Some((before, undo)) = history.pop() {
+                    state.undo(undo);
+                    assert_eq!(before.calculate_hash(), state.calculate_hash());
+                    assert_eq!(before, state);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_position_key_transposition() {
+        let mut state = GameState::new(&GameConfig::new(2, 10, 3), 2).unwrap();
+        state.transition(&Action::ChooseOrientation(FlipHand::DoNotFlip));
+        state.transition(&Action::ChooseOrientation(FlipHand::DoNotFlip));
+        // Put a two card set from player 1 on the board for player 0 to scout from
+        let shown: Vec<OrientedCard> = state.hidden_states[1].hand.drain(..2).collect();
+        state.public_state.board = shown;
+        state.public_state.board_owner = Some(1);
+        state.public_state.card_counts[1] -= 2;
+        state.refresh_position_key();
+
+        // Scouting both cards in either order ends with the same hand
+        let mut first_then_last = state.clone();
+        first_then_last.transition(&Action::PlayScoutToken((
+            PickedCard::FirstCard,
+            0,
+            Orientation::Larger,
+        )));
+        first_then_last.transition(&Action::PlayScoutToken((
+            PickedCard::LastCard,
+            0,
+            Orientation::Larger,
+        )));
+        let mut last_then_first = state.clone();
+        last_then_first.transition(&Action::PlayScoutToken((
+            PickedCard::LastCard,
+            0,
+            Orientation::Larger,
+        )));
+        last_then_first.transition(&Action::PlayScoutToken((
+            PickedCard::FirstCard,
+            1,
+            Orientation::Larger,
+        )));
+
+        assert_eq!(first_then_last.hidden_states, last_then_first.hidden_states);
+        assert_ne!(
+            first_then_last.calculate_hash(),
+            last_then_first.calculate_hash()
+        );
+        assert_eq!(
+            first_then_last.position_key(),
+            last_then_first.position_key()
+        );
+        assert_eq!(
+            first_then_last.compute_position_key(),
+            first_then_last.position_key()
+        );
+        assert_ne!(state.position_key(), first_then_last.position_key());
+
+        // The seed is not part of the key
+        let mut reseeded = first_then_last.clone();
+        reseeded.seed += 1;
+        reseeded.refresh_position_key();
+        assert_eq!(first_then_last.position_key(), reseeded.position_key());
+    }
+
     #[test]
     fn test_shuffle_deck() {
-        let orig = build_deck(4);
+        let orig = build_deck(&GameConfig::new(2, 4, 0));
         let mut deck1 = orig.clone();
         let mut deck2 = orig.clone();
         let mut deck3 = orig.clone();
@@ -683,75 +1220,134 @@ mod tests {
 
     #[test]
     fn test_choose_orientation() {
-        let mut state = GameState::new(10, 3, 2);
-        assert_eq!(true, state.public_state.is_player_one_turn);
-        assert_eq!(false, state.public_state.orientation_chosen);
+        let mut state = GameState::new(&GameConfig::new(2, 10, 3), 2).unwrap();
+        assert_eq!(0, state.public_state.current_player);
+        assert!(!state.public_state.orientation_chosen);
         state.transition(&Action::ChooseOrientation(FlipHand::DoFlip));
-        assert_eq!(false, state.public_state.is_player_one_turn);
-        assert_eq!(false, state.public_state.orientation_chosen);
+        assert_eq!(1, state.public_state.current_player);
+        assert!(!state.public_state.orientation_chosen);
         state.transition(&Action::ChooseOrientation(FlipHand::DoNotFlip));
-        assert_eq!(true, state.public_state.is_player_one_turn);
-        assert_eq!(true, state.public_state.orientation_chosen);
+        assert_eq!(0, state.public_state.current_player);
+        assert!(state.public_state.orientation_chosen);
+        assert_eq!(
+            TransitionResult::IllegalMove(IllegalMoveReason::OrientationChosen),
+            state.transition(&Action::ChooseOrientation(FlipHand::DoFlip))
+        );
     }
+    #[test]
+    fn test_turn_order_rotates() {
+        let mut state = GameState::new(&GameConfig::new(3, 10, 3), 2).unwrap();
+        assert_eq!(3, state.num_players());
+        // 45 cards, trimmed to 42 so that half can be dealt
+        assert_eq!(vec![7; 3], state.public_state.card_counts);
+
+        for player in 0..3 {
+            assert_eq!(player, state.public_state.current_player);
+            assert!(!state.public_state.orientation_chosen);
+            state.transition(&Action::ChooseOrientation(FlipHand::DoNotFlip));
+        }
+        assert_eq!(0, state.public_state.current_player);
+        assert!(state.public_state.orientation_chosen);
+
+        state.transition(&Action::PlayCards(0, 1));
+        assert_eq!(1, state.public_state.current_player);
+        assert_eq!(0, state.public_state.action_history.last().unwrap().0);
+
+        // Scouting keeps the turn, playing passes it on
+        state.transition(&Action::PlayScoutToken((
+            PickedCard::FirstCard,
+            0,
+            Orientation::Larger,
+        )));
+        assert_eq!(1, state.public_state.current_player);
+        let result = state.transition(&Action::PlayCards(0, 1));
+        assert_eq!(TransitionResult::MoveAccepted, result);
+        assert_eq!(2, state.public_state.current_player);
+        assert_eq!(vec![6, 7, 7], state.public_state.card_counts);
+        assert_eq!(vec![0, 0, 0], state.public_state.won_cards);
+        state.display();
+    }
+
+    #[test]
+    fn test_first_player() {
+        let mut state = GameState::new(
+            &GameConfig {
+                first_player: 2,
+                ..GameConfig::new(3, 10, 3)
+            },
+            2,
+        )
+        .unwrap();
+        for player in [2, 0, 1] {
+            assert_eq!(player, state.public_state.current_player);
+            assert!(!state.public_state.orientation_chosen);
+            state.transition(&Action::ChooseOrientation(FlipHand::DoNotFlip));
+        }
+        assert!(state.public_state.orientation_chosen);
+        assert_eq!(2, state.public_state.current_player);
+        state.transition(&Action::PlayCards(0, 1));
+        assert_eq!(0, state.public_state.current_player);
+    }
+
     #[test]
     fn test_play_illegal_cards() {
-        let mut state = GameState::new(10, 3, 2);
+        let mut state = GameState::new(&GameConfig::new(2, 10, 3), 2).unwrap();
         state.transition(&Action::ChooseOrientation(FlipHand::DoFlip));
         state.transition(&Action::ChooseOrientation(FlipHand::DoNotFlip));
         state.display();
 
-        let cards_per_player = state.public_state.player_one_card_count;
+        let cards_per_player = state.public_state.card_counts[0];
 
-        assert_eq!(true, state.public_state.is_player_one_turn);
+        assert_eq!(0, state.public_state.current_player);
 
         let result = state.transition(&Action::PlayCards(0, 3));
         assert!(matches!(result, TransitionResult::IllegalMove(_)));
-        assert_eq!(true, state.public_state.is_player_one_turn);
+        assert_eq!(0, state.public_state.current_player);
 
         let result = state.transition(&Action::PlayCards(100, 0));
         assert!(matches!(result, TransitionResult::IllegalMove(_)));
-        assert_eq!(true, state.public_state.is_player_one_turn);
+        assert_eq!(0, state.public_state.current_player);
 
         let result = state.transition(&Action::PlayCards(1, 1));
         assert!(matches!(result, TransitionResult::IllegalMove(_)));
-        assert_eq!(true, state.public_state.is_player_one_turn);
+        assert_eq!(0, state.public_state.current_player);
 
         let result = state.transition(&Action::PlayCards(1, 0));
         assert!(matches!(result, TransitionResult::IllegalMove(_)));
-        assert_eq!(true, state.public_state.is_player_one_turn);
+        assert_eq!(0, state.public_state.current_player);
 
         let result = state.transition(&Action::PlayCards(cards_per_player, cards_per_player + 1));
         assert!(matches!(result, TransitionResult::IllegalMove(_)));
-        assert_eq!(true, state.public_state.is_player_one_turn);
+        assert_eq!(0, state.public_state.current_player);
     }
 
     #[test]
     fn test_play_same_pair() {
-        let mut state = GameState::new(10, 3, 2);
+        let mut state = GameState::new(&GameConfig::new(2, 10, 3), 2).unwrap();
         state.transition(&Action::ChooseOrientation(FlipHand::DoNotFlip));
         state.transition(&Action::ChooseOrientation(FlipHand::DoNotFlip));
         state.display();
 
-        let played = state.player_one_hidden_state.hand[0..2].to_vec();
+        let played = state.hidden_states[0].hand[0..2].to_vec();
         let result = state.transition(&Action::PlayCards(0, 2));
         state.display();
 
         assert_eq!(TransitionResult::MoveAccepted, result);
-        assert_eq!(false, state.public_state.is_player_one_turn);
+        assert_eq!(1, state.public_state.current_player);
         assert_eq!(played, state.public_state.board);
     }
 
     #[test]
     fn test_play_single() {
-        let mut state = GameState::new(10, 3, 2);
+        let mut state = GameState::new(&GameConfig::new(2, 10, 3), 2).unwrap();
         state.transition(&Action::ChooseOrientation(FlipHand::DoFlip));
         state.transition(&Action::ChooseOrientation(FlipHand::DoNotFlip));
         state.display();
 
-        let played = state.player_one_hidden_state.hand[0..1].to_vec();
+        let played = state.hidden_states[0].hand[0..1].to_vec();
         let result = state.transition(&Action::PlayCards(0, 1));
         assert_eq!(TransitionResult::MoveAccepted, result);
-        assert_eq!(false, state.public_state.is_player_one_turn);
+        assert_eq!(1, state.public_state.current_player);
         assert_eq!(played, state.public_state.board);
     }
 
@@ -784,29 +1380,29 @@ mod tests {
 
     #[test]
     fn test_both_players_act() {
-        let mut state = GameState::new(10, 3, 2);
+        let mut state = GameState::new(&GameConfig::new(2, 10, 3), 2).unwrap();
         state.transition(&Action::ChooseOrientation(FlipHand::DoNotFlip));
         state.transition(&Action::ChooseOrientation(FlipHand::DoNotFlip));
         state.display();
         println!();
-        assert_eq!(2, state.player_one_hidden_state.hand[0].top());
+        assert_eq!(2, state.hidden_states[0].hand[0].top());
 
         // player_one plays a 2
-        let played = state.player_one_hidden_state.hand[0..1].to_vec();
+        let played = state.hidden_states[0].hand[0..1].to_vec();
         let result = state.transition(&Action::PlayCards(0, 1));
         state.display();
         println!();
         assert_eq!(TransitionResult::MoveAccepted, result);
-        assert_eq!(false, state.public_state.is_player_one_turn);
+        assert_eq!(1, state.public_state.current_player);
         assert_eq!(played, state.public_state.board);
 
         // player_two plays a 6
-        let played = state.player_two_hidden_state.hand[0..1].to_vec();
+        let played = state.hidden_states[1].hand[0..1].to_vec();
         let result = state.transition(&Action::PlayCards(0, 1));
         state.display();
         println!();
         assert_eq!(TransitionResult::MoveAccepted, result);
-        assert_eq!(true, state.public_state.is_player_one_turn);
+        assert_eq!(0, state.public_state.current_player);
         assert_eq!(played, state.public_state.board);
     }
 
@@ -837,7 +1433,7 @@ mod tests {
             },
             orientation: Orientation::Larger,
         };
-        let card_set1 = build_card_set(&vec![oc1]);
+        let card_set1 = build_card_set(&[oc1]);
         assert_eq!(Some(CardSet::Same(2, 1)), card_set1);
 
         let oc2 = OrientedCard {
@@ -847,10 +1443,10 @@ mod tests {
             },
             orientation: Orientation::Smaller,
         };
-        let card_set2 = build_card_set(&vec![oc2]);
+        let card_set2 = build_card_set(&[oc2]);
         assert_eq!(Some(CardSet::Same(3, 1)), card_set2);
 
-        let card_set3 = build_card_set(&vec![oc1, oc2]);
+        let card_set3 = build_card_set(&[oc1, oc2]);
         assert_eq!(Some(CardSet::Consecutive(2, 3)), card_set3);
 
         assert!(card_set2 > card_set1);
@@ -860,7 +1456,7 @@ mod tests {
 
     #[test]
     fn test_no_orient() {
-        let mut state = GameState::new(10, 3, 2);
+        let mut state = GameState::new(&GameConfig::new(2, 10, 3), 2).unwrap();
         let result = state.transition(&Action::PlayCards(0, 1));
         assert_eq!(
             TransitionResult::IllegalMove(IllegalMoveReason::MustChooseOrientation),
@@ -881,7 +1477,7 @@ mod tests {
 
     #[test]
     fn test_scout() {
-        let mut state = GameState::new(10, 3, 2);
+        let mut state = GameState::new(&GameConfig::new(2, 10, 3), 2).unwrap();
         state.transition(&Action::ChooseOrientation(FlipHand::DoNotFlip));
         state.transition(&Action::ChooseOrientation(FlipHand::DoNotFlip));
         state.display();
@@ -898,12 +1494,12 @@ mod tests {
         )));
         assert_eq!(TransitionResult::MoveAccepted, result);
         assert_eq!(0, state.public_state.board.len());
-        assert_eq!(12, state.public_state.player_two_card_count);
-        assert_eq!(8, state.player_two_hidden_state.hand[0].top());
+        assert_eq!(12, state.public_state.card_counts[1]);
+        assert_eq!(8, state.hidden_states[1].hand[0].top());
     }
     #[test]
     fn test_bad_scout() {
-        let mut state = GameState::new(10, 3, 2);
+        let mut state = GameState::new(&GameConfig::new(2, 10, 3), 2).unwrap();
         state.transition(&Action::ChooseOrientation(FlipHand::DoNotFlip));
         state.transition(&Action::ChooseOrientation(FlipHand::DoNotFlip));
         state.display();
@@ -962,12 +1558,144 @@ mod tests {
             TransitionResult::IllegalMove(IllegalMoveReason::NoScoutTokens),
             result
         );
-        assert_eq!(0, state.public_state.player_two_scout_token_count);
-        assert_eq!(13, state.public_state.player_two_card_count);
+        assert_eq!(0, state.public_state.scout_token_counts[1]);
+        assert_eq!(13, state.public_state.card_counts[1]);
     }
+    #[test]
+    fn test_scout_and_show() {
+        let mut state = GameState::new(&GameConfig::new(2, 10, 3), 2).unwrap();
+        state.transition(&Action::ChooseOrientation(FlipHand::DoNotFlip));
+        state.transition(&Action::ChooseOrientation(FlipHand::DoNotFlip));
+        state.display();
+
+        state.transition(&Action::PlayCards(0, 1));
+        assert_eq!(2, state.public_state.board[0].top());
+
+        // player_two takes the 2 as an 8, the board is then empty so anything can be shown
+        let scout = (PickedCard::FirstCard, 0u8, Orientation::Larger);
+        let board = state.public_state.board.clone();
+        let result = state.transition(&Action::ScoutAndShow(scout.clone(), (0, 20)));
+        assert_eq!(
+            TransitionResult::IllegalMove(IllegalMoveReason::BadHandIndex),
+            result
+        );
+        assert_eq!(board, state.public_state.board);
+        assert_eq!(11, state.hidden_states[1].hand.len());
+
+        let result = state.transition(&Action::ScoutAndShow(scout.clone(), (0, 1)));
+        state.display();
+        assert_eq!(TransitionResult::MoveAccepted, result);
+        assert_eq!(1, state.public_state.board.len());
+        assert_eq!(8, state.public_state.board[0].top());
+        assert_eq!(11, state.public_state.card_counts[1]);
+        assert_eq!(3, state.public_state.scout_token_counts[1]);
+        assert_eq!(0, state.public_state.won_cards[1]);
+        assert!(state.public_state.scout_and_show_used[1]);
+        assert_eq!(0, state.public_state.current_player);
+
+        let result = state.transition(&Action::ScoutAndShow(scout.clone(), (0, 1)));
+        assert_eq!(TransitionResult::MoveAccepted, result);
+        assert!(state.public_state.scout_and_show_used[0]);
+
+        // The chip can only be used once per round
+        let result = state.transition(&Action::ScoutAndShow(scout, (0, 1)));
+        assert_eq!(
+            TransitionResult::IllegalMove(IllegalMoveReason::ScoutAndShowUsed),
+            result
+        );
+    }
+
+    #[test]
+    fn test_scout_and_show_wins_remaining_board() {
+        let mut state = GameState::new(&GameConfig::new(2, 10, 3), 3).unwrap();
+        state.transition(&Action::ChooseOrientation(FlipHand::DoFlip));
+        state.transition(&Action::ChooseOrientation(FlipHand::DoFlip));
+
+        // Play a 3 card set
+        state.transition(&Action::PlayCards(4, 7));
+        state.display();
+
+        // Taking one end leaves a 2 card set on the board, which must be beaten by the show
+        let scout = (PickedCard::LastCard, 0u8, Orientation::Larger);
+        let result = state.transition(&Action::ScoutAndShow(scout.clone(), (0, 1)));
+        assert_eq!(
+            TransitionResult::IllegalMove(IllegalMoveReason::DoesNotBeatBoard),
+            result
+        );
+        assert_eq!(3, state.public_state.board.len());
+        assert!(!state.public_state.scout_and_show_used[1]);
+    }
+
+    #[test]
+    fn test_award_owner_tokens() {
+        let config = GameConfig {
+            rules: Rules {
+                scout_tokens: ScoutTokenRule::AwardOwner,
+                ..Default::default()
+            },
+            ..GameConfig::new(2, 10, 0)
+        };
+        let mut state = GameState::new(&config, 2).unwrap();
+        state.transition(&Action::ChooseOrientation(FlipHand::DoNotFlip));
+        state.transition(&Action::ChooseOrientation(FlipHand::DoNotFlip));
+
+        state.transition(&Action::PlayCards(0, 1));
+        assert_eq!(Some(0), state.public_state.board_owner);
+
+        // Scouting needs no tokens, ends the turn and pays the owner of the set
+        let scout = Action::PlayScoutToken((PickedCard::FirstCard, 0u8, Orientation::Larger));
+        let result = state.transition(&scout);
+        state.display();
+        assert_eq!(TransitionResult::MoveAccepted, result);
+        assert_eq!(vec![1, 0], state.public_state.scout_token_counts);
+        assert_eq!(vec![10, 12], state.public_state.card_counts);
+        assert_eq!(0, state.public_state.current_player);
+        assert_eq!(None, state.public_state.board_owner);
+
+        state.transition(&Action::PlayCards(0, 1));
+        let result = state.transition(&scout);
+        assert_eq!(TransitionResult::MoveAccepted, result);
+        assert_eq!(vec![2, 0], state.public_state.scout_token_counts);
+
+        // Received tokens score like won cards
+        state.public_state.won_cards[1] = 3;
+        assert_eq!(
+            TransitionResult::GameComplete(vec![2 - 9, 3]),
+            state.build_game_complete(1)
+        );
+    }
+
+    #[test]
+    fn test_return_to_owner() {
+        let mut state = GameState::new(
+            &GameConfig {
+                rules: Rules::published(),
+                ..GameConfig::new(2, 10, 0)
+            },
+            2,
+        )
+        .unwrap();
+        state.transition(&Action::ChooseOrientation(FlipHand::DoNotFlip));
+        state.transition(&Action::ChooseOrientation(FlipHand::DoNotFlip));
+
+        state.transition(&Action::PlayCards(0, 2));
+        assert_eq!(Some(0), state.public_state.board_owner);
+
+        // player_two scouts one card of the pair, play comes back to player_one who still owns
+        // the set. player_one's 9 cards are not counted, player_two has 12.
+        let result = state.transition(&Action::PlayScoutToken((
+            PickedCard::FirstCard,
+            0u8,
+            Orientation::Larger,
+        )));
+        state.display();
+        assert_eq!(TransitionResult::GameComplete(vec![1, -12]), result);
+        assert!(state.public_state.game_complete);
+    }
+
     #[test]
     fn test_illegal_move_reason() {
-        let mut state = GameState::new(10, 3, 3);
+        let mut state = GameState::new(&GameConfig::new(2, 10, 3), 3).unwrap();
         state.transition(&Action::ChooseOrientation(FlipHand::DoFlip));
         state.transition(&Action::ChooseOrientation(FlipHand::DoFlip));
         state.display();
@@ -985,14 +1713,14 @@ mod tests {
                 assert_eq!(IllegalMoveReason::InvalidSet, reason);
             }
             _ => {
-                assert!(false);
+                panic!("expected an illegal move, got {:?}", result);
             }
         }
     }
 
     #[test]
     fn test_won_cards() {
-        let mut state = GameState::new(10, 3, 3);
+        let mut state = GameState::new(&GameConfig::new(2, 10, 3), 3).unwrap();
         state.transition(&Action::ChooseOrientation(FlipHand::DoFlip));
         state.transition(&Action::ChooseOrientation(FlipHand::DoFlip));
         state.display();
@@ -1002,16 +1730,16 @@ mod tests {
 
         state.transition(&Action::PlayCards(1, 2));
         state.display();
-        assert_eq!(true, state.public_state.is_player_one_turn);
-        assert_eq!(1, state.public_state.player_two_won_cards);
-        assert_eq!(0, state.public_state.player_one_won_cards);
+        assert_eq!(0, state.public_state.current_player);
+        assert_eq!(1, state.public_state.won_cards[1]);
+        assert_eq!(0, state.public_state.won_cards[0]);
 
         state.transition(&Action::PlayCards(3, 6));
-        assert_eq!(false, state.public_state.is_player_one_turn);
+        assert_eq!(1, state.public_state.current_player);
         state.display();
 
-        assert_eq!(1, state.public_state.player_two_won_cards);
-        assert_eq!(1, state.public_state.player_one_won_cards);
+        assert_eq!(1, state.public_state.won_cards[1]);
+        assert_eq!(1, state.public_state.won_cards[0]);
         let result = state.transition(&Action::PlayScoutToken((
             PickedCard::FirstCard,
             2,
@@ -1019,21 +1747,21 @@ mod tests {
         )));
         state.display();
         assert_eq!(TransitionResult::MoveAccepted, result);
-        assert_eq!(false, state.public_state.is_player_one_turn);
-        assert_eq!(1, state.public_state.player_two_won_cards);
-        assert_eq!(1, state.public_state.player_one_won_cards);
+        assert_eq!(1, state.public_state.current_player);
+        assert_eq!(1, state.public_state.won_cards[1]);
+        assert_eq!(1, state.public_state.won_cards[0]);
 
         let result = state.transition(&Action::PlayCards(2, 4));
         state.display();
         assert_eq!(TransitionResult::MoveAccepted, result);
-        assert_eq!(true, state.public_state.is_player_one_turn);
-        assert_eq!(3, state.public_state.player_two_won_cards);
-        assert_eq!(1, state.public_state.player_one_won_cards);
+        assert_eq!(0, state.public_state.current_player);
+        assert_eq!(3, state.public_state.won_cards[1]);
+        assert_eq!(1, state.public_state.won_cards[0]);
     }
 
     #[test]
     fn test_game_end() {
-        let mut state = GameState::new(6, 3, 3);
+        let mut state = GameState::new(&GameConfig::new(2, 6, 3), 3).unwrap();
         state.transition(&Action::ChooseOrientation(FlipHand::DoFlip));
         state.transition(&Action::ChooseOrientation(FlipHand::DoFlip));
         state.display();
@@ -1052,43 +1780,43 @@ mod tests {
         state.display();
         // player_one: 1 won card + 2 tokens
         // player_two: 1 won card - 1 card in hand + 3 tokens
-        assert_eq!(TransitionResult::GameComplete(3, 3), result);
+        assert_eq!(TransitionResult::GameComplete(vec![3, 3]), result);
     }
 
     #[test]
     fn test_has_legal_play() {
-        let mut state = GameState::new(6, 0, 3);
+        let mut state = GameState::new(&GameConfig::new(2, 6, 0), 3).unwrap();
         state.transition(&Action::ChooseOrientation(FlipHand::DoFlip));
         state.transition(&Action::ChooseOrientation(FlipHand::DoFlip));
         state.display();
 
         state.transition(&Action::PlayCards(1, 3));
         state.display();
-        assert_eq!(true, state.has_legal_play(false));
+        assert!(state.has_legal_play(1));
 
         state.transition(&Action::PlayCards(1, 3));
         state.display();
-        assert_eq!(false, state.has_legal_play(true));
+        assert!(!state.has_legal_play(0));
     }
 
     #[test]
     fn test_legal_and_beats_board() {
-        let mut state = GameState::new(6, 0, 3);
+        let mut state = GameState::new(&GameConfig::new(2, 6, 0), 3).unwrap();
         state.transition(&Action::ChooseOrientation(FlipHand::DoFlip));
         state.transition(&Action::ChooseOrientation(FlipHand::DoFlip));
         state.display();
         state.transition(&Action::PlayCards(1, 3));
         state.display();
 
-        let proposed_play = state.player_two_hidden_state.hand[1..3].to_vec();
+        let proposed_play = state.hidden_states[1].hand[1..3].to_vec();
         let result = legal_and_beats_board(&state.public_state.board, &proposed_play);
         assert_eq!(None, result);
 
-        let proposed_play = state.player_two_hidden_state.hand[1..2].to_vec();
+        let proposed_play = state.hidden_states[1].hand[1..2].to_vec();
         let result = legal_and_beats_board(&state.public_state.board, &proposed_play);
         assert_eq!(Some(IllegalMoveReason::DoesNotBeatBoard), result);
 
-        let proposed_play = state.player_two_hidden_state.hand[0..2].to_vec();
+        let proposed_play = state.hidden_states[1].hand[0..2].to_vec();
         print!("Proposed play: ");
         print_cards(proposed_play.as_slice());
         println!();
@@ -1099,7 +1827,7 @@ mod tests {
 
     #[test]
     fn test_game_end2() {
-        let mut state = GameState::new(10, 3, 1234);
+        let mut state = GameState::new(&GameConfig::new(2, 10, 3), 1234).unwrap();
         state.transition(&Action::ChooseOrientation(FlipHand::DoNotFlip));
         state.transition(&Action::ChooseOrientation(FlipHand::DoFlip));
         state.display();
@@ -1124,9 +1852,9 @@ mod tests {
             true,
         );
         state.play_and_display(&Action::PlayCards(6, 8), true);
-        let old_won = state.public_state.player_one_won_cards;
+        let old_won = state.public_state.won_cards[0];
         state.play_and_display(&Action::PlayCards(6, 8), true);
-        let new_won = state.public_state.player_one_won_cards;
+        let new_won = state.public_state.won_cards[0];
         assert_eq!(old_won + 2, new_won);
 
         state.play_and_display(&Action::PlayCards(4, 6), true);
@@ -1134,13 +1862,13 @@ mod tests {
         let result = state.play_and_display(&Action::PlayCards(0, 4), true);
         // player_one: 1 won card + 2 tokens
         // player_two: 1 won card - 1 card in hand + 3 tokens
-        assert_eq!(TransitionResult::GameComplete(4, 11), result);
-        assert_eq!(true, state.public_state.game_complete);
+        assert_eq!(TransitionResult::GameComplete(vec![4, 11]), result);
+        assert!(state.public_state.game_complete);
     }
 
     #[test]
     fn test_cant_play_past_end() {
-        let mut state = GameState::new(6, 0, 5);
+        let mut state = GameState::new(&GameConfig::new(2, 6, 0), 5).unwrap();
         state.transition(&Action::ChooseOrientation(FlipHand::DoFlip));
         state.transition(&Action::ChooseOrientation(FlipHand::DoNotFlip));
         state.display();
@@ -1151,7 +1879,7 @@ mod tests {
 
         // Assert result is game end
         let result = state.play_and_display(&Action::PlayCards(0, 2), true);
-        assert!(matches!(result, TransitionResult::GameComplete(_, _)));
+        assert!(matches!(result, TransitionResult::GameComplete(_)));
         assert!(state.public_state.game_complete);
         assert_eq!(5, state.public_state.action_history.len());
 
@@ -1160,8 +1888,28 @@ mod tests {
         assert!(state.public_state.game_complete);
         assert!(matches!(
             state.public_state.action_history.last().unwrap().2,
-            TransitionResult::GameComplete(_, _)
+            TransitionResult::GameComplete(_)
         ));
         assert_eq!(5, state.public_state.action_history.len());
     }
+
+    #[test]
+    fn test_awarded_tokens_saturate() {
+        let config = GameConfig {
+            rules: Rules::published(),
+            ..GameConfig::new(2, 10, 0)
+        };
+        let mut state = GameState::new(&config, 5).unwrap();
+        state.public_state.scout_token_counts[0] = u8::MAX - 1;
+        state.refresh_position_key();
+        while !state.public_state.game_complete {
+            let action =
+                crate::search::MoveIter::new(&state.public_state, state.current_hidden_state())
+                    .next()
+                    .unwrap();
+            state.transition(&action);
+        }
+        let scores = state.final_scores().unwrap();
+        assert_eq!(i8::MAX, scores[0]);
+    }
 }
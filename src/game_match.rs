@@ -0,0 +1,346 @@
+use rand::{RngCore, SeedableRng};
+use rand_xoshiro::SplitMix64;
+
+use crate::arena::{play_game, ArenaConfig, GameRecord};
+use crate::config::{ConfigError, GameConfig};
+use crate::engine::{Action, GameState, PlayerId, TransitionResult};
+use crate::players::player::Player;
+
+/// A full game of Scout: one round per player, the first player rotating each round and the
+/// round scores summed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Match {
+    config: GameConfig,
+    seed: u64,
+
+    /// Scores of each completed round, indexed by PlayerId
+    pub round_scores: Vec<Vec<i8>>,
+}
+
+impl Match {
+    /// config.first_player is ignored, the first player rotates from player 0
+    pub fn new(config: GameConfig, seed: u64) -> Result<Self, ConfigError> {
+        config.validate()?;
+        Ok(Match {
+            config,
+            seed,
+            round_scores: vec![],
+        })
+    }
+
+    pub fn num_players(&self) -> usize {
+        self.config.num_players
+    }
+
+    pub fn num_rounds(&self) -> usize {
+        self.num_players()
+    }
+
+    /// The round to be played next, counting from 0
+    pub fn round(&self) -> usize {
+        self.round_scores.len()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.round() == self.num_rounds()
+    }
+
+    /// Each round is dealt from its own seed, derived from the match seed
+    pub fn round_seed(&self, round: usize) -> u64 {
+        let mut rng = SplitMix64::seed_from_u64(self.seed);
+        (0..round).for_each(|_| {
+            rng.next_u64();
+        });
+        rng.next_u64()
+    }
+
+    /// Deals the next round, None once every round has been played
+    pub fn next_round(&self) -> Option<GameState> {
+        if self.is_complete() {
+            return None;
+        }
+        let round = self.round();
+        // Only the first player differs from the validated config
+        Some(GameState::new(&self.round_config(round), self.round_seed(round)).unwrap())
+    }
+
+    /// The config of the round, the first player rotates
+    fn round_config(&self, round: usize) -> GameConfig {
+        GameConfig {
+            first_player: round % self.num_players(),
+            ..self.config.clone()
+        }
+    }
+
+    /// Records the result that completed the current round
+    pub fn record_round(&mut self, result: &TransitionResult) {
+        debug_assert!(!self.is_complete());
+        match result {
+            TransitionResult::GameComplete(scores) => {
+                debug_assert_eq!(scores.len(), self.num_players());
+                self.round_scores.push(scores.clone());
+            }
+            _ => panic!("Round is not complete: {:?}", result),
+        }
+    }
+
+    /// Plays every remaining round, choose_action is asked for the move of whoever is to act
+    pub fn play<F>(&mut self, mut choose_action: F)
+    where
+        F: FnMut(&GameState) -> Action,
+    {
+        while let Some(mut state) = self.next_round() {
+            loop {
+                let action = choose_action(&state);
+                match state.transition(&action) {
+                    TransitionResult::IllegalMove(reason) => {
+                        panic!("Illegal move ({:?}): {:?}", reason, action);
+                    }
+                    TransitionResult::MoveAccepted => {}
+                    result @ TransitionResult::GameComplete(_) => {
+                        self.record_round(&result);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Plays every remaining round with arena::play_game and the limits of ArenaConfig::new,
+    /// a player per seat, and returns the records of those rounds. A round stopped by the
+    /// arena counts with the scores of its record. new_match is only called when no round
+    /// has been played yet.
+    pub fn play_players(
+        &mut self,
+        players: &mut [Box<dyn Player>],
+    ) -> Result<Vec<GameRecord>, ConfigError> {
+        if players.len() != self.num_players() {
+            return Err(ConfigError::PlayerCount {
+                expected: self.num_players(),
+                given: players.len(),
+            });
+        }
+        if self.round() == 0 {
+            for player in players.iter_mut() {
+                player.new_match(self.num_players());
+            }
+        }
+        let mut records = vec![];
+        while !self.is_complete() {
+            let round = self.round();
+            let config = ArenaConfig::new(self.round_config(round));
+            let record = play_game(&config, self.round_seed(round), players)?;
+            self.record_round(&TransitionResult::GameComplete(record.scores.clone()));
+            records.push(record);
+        }
+        Ok(records)
+    }
+
+    /// Total score of each player over the rounds played so far
+    pub fn scores(&self) -> Vec<i32> {
+        (0..self.num_players())
+            .map(|player| {
+                self.round_scores
+                    .iter()
+                    .map(|scores| scores[player] as i32)
+                    .sum()
+            })
+            .collect()
+    }
+
+    /// Players with the highest total score. Ties are broken by the number of rounds won
+    /// (having the top score of a round, shared or not), then by the best single round.
+    /// Players still tied after that share the win.
+    pub fn winners(&self) -> Vec<PlayerId> {
+        let rounds_won = |player: PlayerId| {
+            self.round_scores
+                .iter()
+                .filter(|scores| scores[player] == *scores.iter().max().unwrap())
+                .count()
+        };
+        let best_round = |player: PlayerId| {
+            self.round_scores
+                .iter()
+                .map(|scores| scores[player])
+                .max()
+                .unwrap_or(0)
+        };
+
+        let scores = self.scores();
+        let rank = |player: PlayerId| (scores[player], rounds_won(player), best_round(player));
+        let best = (0..self.num_players()).map(rank).max().unwrap();
+        (0..self.num_players())
+            .filter(|&player| rank(player) == best)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::arena::GameEnd;
+    use crate::engine::{PlayerHiddenState, PublicState};
+    use crate::players::player::{Stateless, StatelessPlayer};
+    use crate::players::trivial_player::TrivialPlayer;
+    use crate::search::MoveIter;
+
+    #[test]
+    fn test_rounds_rotate() {
+        let mut game_match = Match::new(GameConfig::new(3, 10, 3), 7).unwrap();
+        assert_eq!(3, game_match.num_rounds());
+
+        let mut seeds = vec![];
+        for round in 0..3 {
+            let state = game_match.next_round().unwrap();
+            assert_eq!(round, state.public_state.first_player);
+            assert_eq!(round, state.public_state.current_player);
+            seeds.push(state.seed);
+            game_match.record_round(&TransitionResult::GameComplete(vec![1, 2, 3]));
+        }
+        assert!(game_match.is_complete());
+        assert!(game_match.next_round().is_none());
+        seeds.dedup();
+        assert_eq!(3, seeds.len());
+
+        assert_eq!(vec![3, 6, 9], game_match.scores());
+        assert_eq!(vec![2], game_match.winners());
+    }
+
+    #[test]
+    fn test_tie_breaks() {
+        let mut game_match = Match::new(GameConfig::new(3, 10, 3), 7).unwrap();
+        game_match.record_round(&TransitionResult::GameComplete(vec![5, 0, 5]));
+        game_match.record_round(&TransitionResult::GameComplete(vec![0, 5, -2]));
+        game_match.record_round(&TransitionResult::GameComplete(vec![0, 0, 2]));
+        // All on 5 points, player 0 and 1 won a round each, player 2 two
+        assert_eq!(vec![5, 5, 5], game_match.scores());
+        assert_eq!(vec![2], game_match.winners());
+
+        let mut game_match = Match::new(GameConfig::new(2, 10, 3), 7).unwrap();
+        game_match.record_round(&TransitionResult::GameComplete(vec![6, 1]));
+        game_match.record_round(&TransitionResult::GameComplete(vec![-1, 4]));
+        // Both on 5 points with a round won each, player 0 had the best round
+        assert_eq!(vec![0], game_match.winners());
+
+        let mut game_match = Match::new(GameConfig::new(2, 10, 3), 7).unwrap();
+        game_match.record_round(&TransitionResult::GameComplete(vec![4, 1]));
+        game_match.record_round(&TransitionResult::GameComplete(vec![1, 4]));
+        assert_eq!(vec![0, 1], game_match.winners());
+    }
+
+    #[test]
+    fn test_play() {
+        let mut game_match = Match::new(GameConfig::published(4).unwrap(), 1).unwrap();
+        game_match.play(|state| {
+            MoveIter::new(&state.public_state, state.current_hidden_state())
+                .next()
+                .unwrap()
+        });
+        assert!(game_match.is_complete());
+        assert_eq!(4, game_match.round_scores.len());
+        assert!(!game_match.winners().is_empty());
+    }
+
+    #[derive(Default)]
+    struct Calls {
+        matches: usize,
+        rounds: usize,
+        round_scores: Vec<Vec<i8>>,
+    }
+
+    /// Plays like TrivialPlayer and checks the calls to the hooks
+    #[derive(Default)]
+    struct CountingPlayer {
+        calls: Rc<RefCell<Calls>>,
+        seat: Option<PlayerId>,
+        moves: usize,
+        own_moves: usize,
+        observed: usize,
+    }
+
+    impl Player for CountingPlayer {
+        fn new_match(&mut self, num_players: usize) {
+            assert_eq!(3, num_players);
+            self.calls.borrow_mut().matches += 1;
+        }
+
+        fn new_round(&mut self, seat: PlayerId, public_state: &PublicState) {
+            assert!(public_state.action_history.is_empty());
+            self.seat = Some(seat);
+            self.calls.borrow_mut().rounds += 1;
+        }
+
+        fn choose_action(
+            &mut self,
+            public_state: &PublicState,
+            hidden_state: &PlayerHiddenState,
+        ) -> Action {
+            assert_eq!(self.seat, Some(public_state.current_player));
+            // Every earlier move of the round was observed
+            assert_eq!(self.observed, public_state.action_history.len());
+            self.moves += 1;
+            TrivialPlayer {}.choose_action(public_state, hidden_state)
+        }
+
+        fn observe(&mut self, _action: &Action, result: &TransitionResult, actor: PlayerId) {
+            assert!(!matches!(result, TransitionResult::IllegalMove(_)));
+            self.own_moves += (Some(actor) == self.seat) as usize;
+            self.observed += 1;
+        }
+
+        fn end_round(&mut self, scores: &[i8]) {
+            assert_eq!(self.moves, self.own_moves);
+            self.calls.borrow_mut().round_scores.push(scores.to_vec());
+            self.observed = 0;
+        }
+    }
+
+    #[test]
+    fn test_play_players() {
+        let config = GameConfig::new(3, 10, 3);
+        let calls = Rc::new(RefCell::new(Calls::default()));
+        let counting = || CountingPlayer {
+            calls: calls.clone(),
+            ..CountingPlayer::default()
+        };
+        let mut players: Vec<Box<dyn Player>> = vec![
+            Box::new(counting()),
+            Box::new(Stateless(TrivialPlayer {})),
+            Box::new(counting()),
+        ];
+        let mut game_match = Match::new(config.clone(), 3).unwrap();
+        let records = game_match.play_players(&mut players).unwrap();
+        assert!(game_match.is_complete());
+        assert_eq!(3, records.len());
+        assert!(records.iter().all(|record| record.end == GameEnd::Complete));
+        assert_eq!(
+            Err(ConfigError::PlayerCount {
+                expected: 3,
+                given: 2
+            }),
+            Match::new(config.clone(), 3)
+                .unwrap()
+                .play_players(&mut players[..2])
+        );
+        let calls = calls.borrow();
+        assert_eq!(2, calls.matches);
+        assert_eq!(2 * 3, calls.rounds);
+        // Both counting players saw the end of each round
+        let round_scores: Vec<Vec<i8>> = game_match
+            .round_scores
+            .iter()
+            .flat_map(|scores| [scores.clone(), scores.clone()])
+            .collect();
+        assert_eq!(round_scores, calls.round_scores);
+
+        // Stateless players play as before
+        let mut trivial_match = Match::new(config, 3).unwrap();
+        trivial_match.play(|state| {
+            TrivialPlayer {}.choose_action(&state.public_state, state.current_hidden_state())
+        });
+        assert_eq!(trivial_match.round_scores, game_match.round_scores);
+    }
+}
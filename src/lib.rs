@@ -0,0 +1,15 @@
+pub mod arena;
+pub mod batch_env;
+pub mod best_response;
+pub mod cfr;
+pub mod config;
+pub mod determinize;
+pub mod encoding;
+pub mod engine;
+pub mod game_match;
+pub mod players;
+pub mod search;
+pub mod view;
+
+#[cfg(feature = "python")]
+mod python;
@@ -1,22 +1,93 @@
-mod engine;
-mod players;
-mod search;
-
 use std::env;
 
+use scout_engine::{config::GameConfig, engine, search};
+
+/// Usage:
+///   <max card num> <scout tokens> <seed> [num players]
+///   dedup <max card num> <scout tokens> <seed> [num players] [table MB]
+///   perft <max card num> <scout tokens> <seed> <depth> [num players]
+///   divide <max card num> <scout tokens> <seed> <depth> [num players]
+///   solve <max card num> <scout tokens> <seed> [table MB]
+/// dedup reports how much of the game tree is shared between move orders, perft counts the
+/// leaves of the tree cut at depth and divide splits that count by the first move. solve
+/// prints the value of a two player game with every hand visible and a line of best play.
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = env::args().collect();
+    let command = match args.get(1).map(String::as_str) {
+        Some("dedup" | "perft" | "divide" | "solve") => args.remove(1),
+        _ => String::from("walk"),
+    };
     let num_cards = args[1].parse::<u8>().unwrap();
     let num_scout = args[2].parse::<u8>().unwrap();
     let seed = args[3].parse::<u64>().unwrap();
+    let depth = if command == "perft" || command == "divide" {
+        Some(args.remove(4).parse::<usize>().unwrap())
+    } else {
+        None
+    };
+    let num_players = if command == "solve" {
+        2
+    } else {
+        args.get(4).map_or(2, |n| n.parse::<usize>().unwrap())
+    };
 
-    let state = engine::GameState::new(num_cards, num_scout, seed);
-    let mut count = 0;
-    let mut count_fn = |_: engine::GameState| {
-        count += 1;
+    let config = GameConfig::new(num_players, num_cards, num_scout);
+    let mut state = match engine::GameState::new(&config, seed) {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!("Invalid game config: {}", err);
+            std::process::exit(1);
+        }
     };
-    search::walk_games(state, &mut count_fn);
-    //let tree = search::tree_from_game_state(state, 100);
-    //let num_terminal_nodes = tree.num_terminal_nodes();
-    println!("Number of games: {}", count);
+
+    match command.as_str() {
+        "dedup" => {
+            let table_mb = args.get(5).map_or(64, |n| n.parse::<usize>().unwrap());
+            let mut table = search::TranspositionTable::new(table_mb << 20);
+            let counts = search::count_games(&mut state, &mut table);
+            println!("Number of games: {}", counts.tree.games);
+            println!("Distinct terminal positions: {}", counts.terminal_positions);
+            println!("Raw nodes: {}", counts.tree.nodes);
+            println!(
+                "Deduplicated nodes: {} ({} table hits, {} table entries)",
+                counts.visited_nodes,
+                counts.table_hits,
+                table.len()
+            );
+        }
+        "perft" => {
+            println!("Leaves: {}", search::perft(&mut state, depth.unwrap()));
+        }
+        "divide" => {
+            let divided = search::divide(&mut state, depth.unwrap());
+            for (action, count) in &divided {
+                println!("{:?}: {}", action, count);
+            }
+            println!("Moves: {}", divided.len());
+            println!(
+                "Leaves: {}",
+                divided.iter().map(|(_, count)| count).sum::<u64>()
+            );
+        }
+        "solve" => {
+            let table_mb = args.get(4).map_or(64, |n| n.parse::<usize>().unwrap());
+            let mut table = search::TranspositionTable::new(table_mb << 20);
+            let solution = search::solve(&mut state, &mut table);
+            for action in &solution.principal_variation {
+                println!("{:?}", action);
+            }
+            println!("Value for player 0: {}", solution.value);
+            println!("Nodes: {}", solution.nodes);
+        }
+        _ => {
+            let mut count = 0;
+            let mut count_fn = |_: &engine::GameState| {
+                count += 1;
+            };
+            search::walk_games(&mut state, &mut count_fn);
+            //let tree = search::tree_from_game_state(state, 100);
+            //let num_terminal_nodes = tree.num_terminal_nodes();
+            println!("Number of games: {}", count);
+        }
+    }
 }
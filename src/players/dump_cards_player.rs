@@ -0,0 +1,56 @@
+use crate::engine::{self, Action, FlipHand};
+use crate::players::player::StatelessPlayer;
+use crate::search::MoveIter;
+
+/// Takes the move leaving the fewest cards in hand, the first such move in MoveIter order.
+/// Keeps the hand as dealt.
+pub struct DumpCardsPlayer {}
+
+/// How the move changes the number of cards in hand
+fn hand_size_change(action: &Action) -> i32 {
+    match action {
+        Action::ChooseOrientation(_) => 0,
+        Action::PlayCards(start_idx, end_idx) => *start_idx as i32 - *end_idx as i32,
+        Action::PlayScoutToken(_) => 1,
+        Action::ScoutAndShow(_, (start_idx, end_idx)) => 1 + *start_idx as i32 - *end_idx as i32,
+    }
+}
+
+impl StatelessPlayer for DumpCardsPlayer {
+    fn choose_action(
+        &self,
+        public_state: &engine::PublicState,
+        hidden_state: &engine::PlayerHiddenState,
+    ) -> engine::Action {
+        if !public_state.orientation_chosen {
+            return Action::ChooseOrientation(FlipHand::DoNotFlip);
+        }
+        MoveIter::new(public_state, hidden_state)
+            .min_by_key(hand_size_change)
+            .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::GameConfig;
+    use crate::engine::GameState;
+
+    #[test]
+    fn test_dumps_most_cards() {
+        let mut state = GameState::new(&GameConfig::new(2, 10, 3), 1).unwrap();
+        let player = DumpCardsPlayer {};
+        while !state.public_state.game_complete {
+            let before = state.current_hidden_state().hand.len() as i32;
+            let action = player.choose_action(&state.public_state, state.current_hidden_state());
+            let best = MoveIter::new(&state.public_state, state.current_hidden_state())
+                .map(|action| hand_size_change(&action))
+                .min()
+                .unwrap();
+            let actor = state.public_state.current_player;
+            state.transition(&action);
+            assert_eq!(before + best, state.hidden_states[actor].hand.len() as i32);
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use crate::engine::{self, build_card_set, Action, FlipHand};
+use crate::players::player::StatelessPlayer;
+use crate::search::MoveIter;
+
+/// Plays the strongest set that beats the board, which is the biggest one. Keeps the hand
+/// as dealt and takes the first other move when it cannot play.
+pub struct GreedyPlayer {}
+
+impl StatelessPlayer for GreedyPlayer {
+    fn choose_action(
+        &self,
+        public_state: &engine::PublicState,
+        hidden_state: &engine::PlayerHiddenState,
+    ) -> engine::Action {
+        if !public_state.orientation_chosen {
+            return Action::ChooseOrientation(FlipHand::DoNotFlip);
+        }
+        let hand = &hidden_state.hand;
+        let mut moves = MoveIter::new(public_state, hidden_state);
+        let first = moves.next().unwrap();
+        let strongest_play = std::iter::once(first.clone())
+            .chain(moves)
+            .filter_map(|action| match action {
+                Action::PlayCards(start_idx, end_idx) => {
+                    build_card_set(&hand[start_idx as usize..end_idx as usize])
+                        .map(|card_set| (action, card_set))
+                }
+                _ => None,
+            })
+            .reduce(|best, play| if play.1 > best.1 { play } else { best });
+        strongest_play.map_or(first, |(action, _)| action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::GameConfig;
+    use crate::engine::{GameState, Orientation, OrientedCard};
+
+    #[test]
+    fn test_plays_biggest_set() {
+        let mut state = GameState::new(&GameConfig::new(2, 10, 3), 1).unwrap();
+        let player = GreedyPlayer {};
+        let action = player.choose_action(&state.public_state, state.current_hidden_state());
+        assert_eq!(Action::ChooseOrientation(FlipHand::DoNotFlip), action);
+        state.transition(&action);
+        state.transition(&action);
+
+        state.hidden_states[0].hand = [(3, 4), (1, 5), (2, 5), (5, 6), (7, 8)]
+            .into_iter()
+            .map(|numbers| OrientedCard::new(numbers, Orientation::Larger))
+            .collect();
+        // 4 5 5 6 8, the pair of 5s beats any single or the 5 6 run
+        assert_eq!(
+            Action::PlayCards(1, 3),
+            player.choose_action(&state.public_state, state.current_hidden_state())
+        );
+    }
+}
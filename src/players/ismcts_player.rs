@@ -0,0 +1,297 @@
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+use rand::seq::IteratorRandom;
+use rand::{Rng, SeedableRng};
+use rand_xoshiro::SplitMix64;
+
+use crate::config::GameConfig;
+use crate::determinize::sample_determinization;
+use crate::engine::{self, Action, GameState, PlayerId};
+use crate::players::mcts_player::rollout;
+use crate::players::player::Player;
+use crate::search::MoveIter;
+use crate::view::PlayerView;
+
+/// Search settings of an IsmctsPlayer
+#[derive(Debug, Clone)]
+pub struct IsmctsConfig {
+    /// Iterations per move, each with a newly sampled deal
+    pub iterations: usize,
+    /// Stops the search of a move after this long, even with iterations left
+    pub time_budget: Option<Duration>,
+    /// UCT exploration constant, rewards are between 0 and 1
+    pub exploration: f64,
+    pub seed: u64,
+}
+
+impl Default for IsmctsConfig {
+    fn default() -> Self {
+        IsmctsConfig {
+            iterations: 2000,
+            time_budget: None,
+            exploration: 0.7,
+            seed: 0,
+        }
+    }
+}
+
+struct Node {
+    /// The move leading to this node and the player who made it, None at the root
+    edge: Option<(PlayerId, Action)>,
+    children: Vec<usize>,
+    visits: u32,
+    /// Iterations in which the move leading here was legal when its parent was passed
+    availability: u32,
+    /// Sum of the rewards of the player who made the move leading here
+    total_reward: f64,
+}
+
+impl Node {
+    fn new(edge: Option<(PlayerId, Action)>) -> Self {
+        Node {
+            edge,
+            children: vec![],
+            visits: 0,
+            availability: 0,
+            total_reward: 0.0,
+        }
+    }
+
+    fn action(&self) -> &Action {
+        &self.edge.as_ref().unwrap().1
+    }
+}
+
+/// Single observer information set MCTS. One tree is searched, its nodes are the move
+/// sequences from the position, so each node stands for the information set of the player
+/// who decides there rather than for one deal. Every iteration samples a new deal from what
+/// the player has seen (see determinize) and only walks the moves legal in it. A move that is
+/// legal in some deals only is scored against the number of iterations it was available in.
+pub struct IsmctsPlayer {
+    game_config: GameConfig,
+    config: IsmctsConfig,
+    rng: SplitMix64,
+}
+
+impl IsmctsPlayer {
+    /// game_config is the config of the games played, the first player is taken from the game
+    pub fn new(game_config: GameConfig, config: IsmctsConfig) -> Self {
+        let rng = SplitMix64::seed_from_u64(config.seed);
+        IsmctsPlayer {
+            game_config,
+            config,
+            rng,
+        }
+    }
+
+    fn iterate<R: Rng>(&self, nodes: &mut Vec<Node>, mut state: GameState, rng: &mut R) {
+        let mut node = 0;
+        let mut path = vec![node];
+        while !state.public_state.game_complete {
+            let actor = state.public_state.current_player;
+            let legal: Vec<Action> =
+                MoveIter::new(&state.public_state, state.current_hidden_state()).collect();
+            let legal_set: HashSet<&Action> = legal.iter().collect();
+            let available: Vec<usize> = nodes[node]
+                .children
+                .iter()
+                .copied()
+                .filter(|&child| legal_set.contains(nodes[child].action()))
+                .collect();
+            // Counted whether this step expands or selects
+            for &child in &available {
+                nodes[child].availability += 1;
+            }
+
+            // Expansion, with a move of this deal not tried yet
+            if available.len() < legal.len() {
+                let tried: HashSet<&Action> = available
+                    .iter()
+                    .map(|&child| nodes[child].action())
+                    .collect();
+                let action = legal
+                    .iter()
+                    .filter(|action| !tried.contains(action))
+                    .choose(rng)
+                    .unwrap()
+                    .clone();
+                state.transition(&action);
+                let child = nodes.len();
+                let mut expanded = Node::new(Some((actor, action)));
+                expanded.availability = 1;
+                nodes.push(expanded);
+                nodes[node].children.push(child);
+                path.push(child);
+                break;
+            }
+
+            // Selection among the moves available in this deal
+            let uct = |child: usize| {
+                let child = &nodes[child];
+                child.total_reward / child.visits as f64
+                    + self.config.exploration
+                        * ((child.availability as f64).ln() / child.visits as f64).sqrt()
+            };
+            node = available
+                .into_iter()
+                .max_by(|&a, &b| uct(a).total_cmp(&uct(b)))
+                .unwrap();
+            state.transition(nodes[node].action());
+            path.push(node);
+        }
+
+        let rewards = rollout(&mut state, rng);
+        for node in path {
+            let node = &mut nodes[node];
+            node.visits += 1;
+            if let Some((actor, _)) = node.edge {
+                node.total_reward += rewards[actor];
+            }
+        }
+    }
+
+    /// The searched tree, its root is the position of the view
+    fn search(&self, view: &PlayerView, rng: &mut SplitMix64) -> Vec<Node> {
+        let start = Instant::now();
+        let game_config = GameConfig {
+            first_player: view.public_state().first_player,
+            ..self.game_config.clone()
+        };
+        let mut nodes = vec![Node::new(None)];
+        for _ in 0..self.config.iterations {
+            if let Some(time_budget) = self.config.time_budget {
+                if start.elapsed() >= time_budget {
+                    break;
+                }
+            }
+            // None only if no deal fits what the player has seen, which the sampler should
+            // not allow
+            let Some(state) = sample_determinization(view, &game_config, rng) else {
+                break;
+            };
+            self.iterate(&mut nodes, state, rng);
+        }
+        nodes
+    }
+}
+
+impl Player for IsmctsPlayer {
+    fn choose_action(
+        &mut self,
+        public_state: &engine::PublicState,
+        hidden_state: &engine::PlayerHiddenState,
+    ) -> engine::Action {
+        // search borrows the player, the generator is put back after it
+        let mut rng = self.rng.clone();
+        let view = PlayerView::from_states(public_state, hidden_state);
+        let nodes = self.search(&view, &mut rng);
+        // The player's own moves are legal in every deal, so all are root children after
+        // enough iterations
+        let action = match nodes[0]
+            .children
+            .iter()
+            .max_by_key(|&&child| nodes[child].visits)
+        {
+            Some(&child) => nodes[child].action().clone(),
+            None => view.moves().choose(&mut rng).unwrap(),
+        };
+        self.rng = rng;
+        action
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arena::{play_game, ArenaConfig, GameEnd};
+    use crate::players::random_player::RandomPlayer;
+
+    fn ismcts_config(seed: u64) -> IsmctsConfig {
+        IsmctsConfig {
+            iterations: 100,
+            seed,
+            ..IsmctsConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_beats_random_player() {
+        let config = GameConfig::new(2, 8, 2);
+        let mut num_won = 0;
+        for seed in 0..4 {
+            for seat in 0..2 {
+                let mut players: Vec<Box<dyn Player>> = vec![
+                    Box::new(IsmctsPlayer::new(config.clone(), ismcts_config(seed))),
+                    Box::new(RandomPlayer::new(seed)),
+                ];
+                players.swap(0, seat);
+                let record =
+                    play_game(&ArenaConfig::new(config.clone()), seed, &mut players).unwrap();
+                assert_eq!(GameEnd::Complete, record.end);
+                num_won += (record.scores[seat] > record.scores[1 - seat]) as usize;
+            }
+        }
+        assert!(num_won >= 6, "{}", num_won);
+    }
+
+    #[test]
+    fn test_moves_available_in_some_deals() {
+        let config = GameConfig::new(3, 10, 2);
+        let mut state = GameState::new(&config, 2).unwrap();
+        for _ in 0..3 {
+            state.transition(&Action::ChooseOrientation(engine::FlipHand::DoNotFlip));
+        }
+        let player = IsmctsPlayer::new(config, ismcts_config(3));
+        let view = PlayerView::new(&state, 0);
+        let nodes = player.search(&view, &mut SplitMix64::seed_from_u64(3));
+
+        // Every iteration goes through one of the player's own moves
+        let root = &nodes[0];
+        assert_eq!(100, root.visits);
+        assert_eq!(
+            root.visits,
+            root.children
+                .iter()
+                .map(|&child| nodes[child].visits)
+                .sum::<u32>()
+        );
+        // A move is available in every iteration that visits it
+        assert!(nodes[1..]
+            .iter()
+            .all(|node| node.availability >= node.visits));
+
+        // The replies of the next player depend on their hidden hand, some replies in the tree
+        // are not legal in the real game
+        let mut num_illegal_replies = 0;
+        for &child in &root.children {
+            let mut after = state.clone();
+            after.transition(nodes[child].action());
+            if after.public_state.game_complete || after.public_state.current_player != 1 {
+                continue;
+            }
+            let legal: HashSet<Action> =
+                MoveIter::new(&after.public_state, after.current_hidden_state()).collect();
+            for &reply in &nodes[child].children {
+                if !legal.contains(nodes[reply].action()) {
+                    num_illegal_replies += 1;
+                    assert!(nodes[reply].availability < nodes[child].visits);
+                }
+            }
+        }
+        assert!(num_illegal_replies > 0);
+    }
+
+    #[test]
+    fn test_seeded() {
+        let config = GameConfig::published(3).unwrap();
+        let state = GameState::new(&config, 4).unwrap();
+        let actions: Vec<Action> = (0..2)
+            .map(|_| {
+                IsmctsPlayer::new(config.clone(), ismcts_config(5))
+                    .choose_action(&state.public_state, state.current_hidden_state())
+            })
+            .collect();
+        assert_eq!(actions[0], actions[1]);
+    }
+}
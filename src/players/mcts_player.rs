@@ -0,0 +1,385 @@
+use std::time::{Duration, Instant};
+
+use rand::seq::{IteratorRandom, SliceRandom};
+use rand::{Rng, SeedableRng};
+use rand_xoshiro::SplitMix64;
+
+use crate::config::GameConfig;
+use crate::determinize::sample_determinization;
+use crate::engine::{self, Action, GameState, PlayerId, TransitionResult};
+use crate::players::player::Player;
+use crate::search::MoveIter;
+use crate::view::PlayerView;
+
+/// Search settings of an MctsPlayer
+#[derive(Debug, Clone)]
+pub struct MctsConfig {
+    /// Iterations per move, shared between the determinizations
+    pub iterations: usize,
+    /// Stops the search of a move after this long, even with iterations left
+    pub time_budget: Option<Duration>,
+    /// UCT exploration constant, rewards are between 0 and 1
+    pub exploration: f64,
+    /// Deals sampled per move, each searched with its own tree
+    pub determinizations: usize,
+    pub seed: u64,
+}
+
+impl Default for MctsConfig {
+    fn default() -> Self {
+        MctsConfig {
+            iterations: 1000,
+            time_budget: None,
+            exploration: std::f64::consts::SQRT_2,
+            determinizations: 8,
+            seed: 0,
+        }
+    }
+}
+
+struct Node {
+    /// The move leading to this node and the player who made it, None at the root
+    edge: Option<(PlayerId, Action)>,
+    children: Vec<usize>,
+    /// Legal moves without a child yet
+    untried: Vec<Action>,
+    visits: u32,
+    /// Sum of the rewards of the player who made the move leading here
+    total_reward: f64,
+}
+
+impl Node {
+    fn new(edge: Option<(PlayerId, Action)>, state: &GameState) -> Self {
+        let untried = if state.public_state.game_complete {
+            vec![]
+        } else {
+            MoveIter::new(&state.public_state, state.current_hidden_state()).collect()
+        };
+        Node {
+            edge,
+            children: vec![],
+            untried,
+            visits: 0,
+            total_reward: 0.0,
+        }
+    }
+}
+
+/// A UCT tree over one sampled deal, rooted at the position the player last decided in
+struct DeterminizedSearch {
+    state: GameState,
+    nodes: Vec<Node>,
+    root: usize,
+}
+
+impl DeterminizedSearch {
+    fn new(state: GameState) -> Self {
+        let root = Node::new(None, &state);
+        DeterminizedSearch {
+            state,
+            nodes: vec![root],
+            root: 0,
+        }
+    }
+
+    /// Plays a move of the real game, keeping the subtree below it. False when the sampled
+    /// deal does not agree with the real result.
+    fn advance(&mut self, action: &Action, result: &TransitionResult) -> bool {
+        if self.state.transition(action) != *result {
+            return false;
+        }
+        let child = self.nodes[self.root].children.iter().copied().find(|&child| {
+            matches!(&self.nodes[child].edge, Some((_, edge_action)) if edge_action == action)
+        });
+        match child {
+            Some(child) => self.root = child,
+            None => *self = DeterminizedSearch::new(self.state.clone()),
+        }
+        true
+    }
+
+    fn iterate<R: Rng>(&mut self, exploration: f64, rng: &mut R) {
+        let mut state = self.state.clone();
+        let mut node = self.root;
+        let mut path = vec![node];
+
+        // Selection
+        while self.nodes[node].untried.is_empty() && !self.nodes[node].children.is_empty() {
+            let log_visits = (self.nodes[node].visits as f64).ln();
+            node = *self.nodes[node]
+                .children
+                .iter()
+                .max_by(|&&a, &&b| {
+                    let uct = |child: usize| {
+                        let child = &self.nodes[child];
+                        child.total_reward / child.visits as f64
+                            + exploration * (log_visits / child.visits as f64).sqrt()
+                    };
+                    uct(a).total_cmp(&uct(b))
+                })
+                .unwrap();
+            let (_, action) = self.nodes[node].edge.as_ref().unwrap();
+            state.transition(action);
+            path.push(node);
+        }
+
+        // Expansion
+        if !self.nodes[node].untried.is_empty() {
+            let untried = &mut self.nodes[node].untried;
+            let action = untried.swap_remove(rng.gen_range(0..untried.len()));
+            let actor = state.public_state.current_player;
+            state.transition(&action);
+            let child = self.nodes.len();
+            self.nodes.push(Node::new(Some((actor, action)), &state));
+            self.nodes[node].children.push(child);
+            path.push(child);
+        }
+
+        // Random rollout
+        let rewards = rollout(&mut state, rng);
+        for node in path {
+            let node = &mut self.nodes[node];
+            node.visits += 1;
+            if let Some((actor, _)) = node.edge {
+                node.total_reward += rewards[actor];
+            }
+        }
+    }
+}
+
+/// Plays random moves to the end of the round. A player's reward grows with their lead over
+/// the best other player, from 0 to 1 and 0.5 for a tie.
+pub(crate) fn rollout<R: Rng>(state: &mut GameState, rng: &mut R) -> Vec<f64> {
+    if !state.public_state.game_complete {
+        loop {
+            let action = MoveIter::new(&state.public_state, state.current_hidden_state())
+                .choose(rng)
+                .unwrap();
+            if let TransitionResult::GameComplete(_) = state.transition(&action) {
+                break;
+            }
+        }
+    }
+    let scores = state
+        .final_scores()
+        .unwrap_or_else(|| panic!("Game complete without final scores (seed:{:?})", state.seed));
+    (0..scores.len())
+        .map(|player| {
+            let best_other = (0..scores.len())
+                .filter(|&other| other != player)
+                .map(|other| scores[other])
+                .max()
+                .unwrap();
+            0.5 + 0.5 * ((scores[player] - best_other) as f64 / 5.0).tanh()
+        })
+        .collect()
+}
+
+struct MctsState {
+    rng: SplitMix64,
+    searches: Vec<DeterminizedSearch>,
+}
+
+impl MctsState {
+    /// Moves the kept trees to the current position, dropping those that disagree with it
+    fn reuse_searches(&mut self, view: &PlayerView) {
+        let history = &view.public_state().action_history;
+        for mut search in std::mem::take(&mut self.searches) {
+            let history_len = search.state.public_state.action_history.len();
+            if history_len > history.len()
+                || search.state.public_state.action_history[..] != history[..history_len]
+            {
+                continue;
+            }
+            let agrees = history[history_len..]
+                .iter()
+                .all(|(_, action, result)| search.advance(action, result));
+            if agrees && search.state.hidden_states[view.player()] == *view.hidden_state() {
+                self.searches.push(search);
+            }
+        }
+    }
+}
+
+/// Monte Carlo tree search with UCT. The hands the player cannot see are sampled again and
+/// again from what the player has seen (see determinize), each sample is searched with its
+/// own tree and the move visited the most over all trees is played. Trees whose sample still
+/// agrees with the game are kept for the next move.
+pub struct MctsPlayer {
+    game_config: GameConfig,
+    config: MctsConfig,
+    state: MctsState,
+}
+
+impl MctsPlayer {
+    /// game_config is the config of the games played, the first player is taken from the game
+    pub fn new(game_config: GameConfig, config: MctsConfig) -> Self {
+        let rng = SplitMix64::seed_from_u64(config.seed);
+        MctsPlayer {
+            game_config,
+            config,
+            state: MctsState {
+                rng,
+                searches: vec![],
+            },
+        }
+    }
+}
+
+impl Player for MctsPlayer {
+    fn new_round(&mut self, _seat: PlayerId, _public_state: &engine::PublicState) {
+        self.state.searches.clear();
+    }
+
+    fn choose_action(
+        &mut self,
+        public_state: &engine::PublicState,
+        hidden_state: &engine::PlayerHiddenState,
+    ) -> engine::Action {
+        let start = Instant::now();
+        let mcts = &mut self.state;
+        let view = PlayerView::from_states(public_state, hidden_state);
+        mcts.reuse_searches(&view);
+
+        let game_config = GameConfig {
+            first_player: public_state.first_player,
+            ..self.game_config.clone()
+        };
+        while mcts.searches.len() < self.config.determinizations.max(1) {
+            match sample_determinization(&view, &game_config, &mut mcts.rng) {
+                Some(state) => mcts.searches.push(DeterminizedSearch::new(state)),
+                None => break,
+            }
+        }
+        if mcts.searches.is_empty() {
+            // No deal fits what the player has seen, which the sampler should not allow
+            return view.moves().choose(&mut mcts.rng).unwrap();
+        }
+
+        for iteration in 0..self.config.iterations {
+            if let Some(time_budget) = self.config.time_budget {
+                if start.elapsed() >= time_budget {
+                    break;
+                }
+            }
+            let num_searches = mcts.searches.len();
+            mcts.searches[iteration % num_searches].iterate(self.config.exploration, &mut mcts.rng);
+        }
+
+        // The player's own moves are the same in every sample
+        let mut actions: Vec<Action> = view.moves().collect();
+        actions.shuffle(&mut mcts.rng);
+        actions
+            .into_iter()
+            .max_by_key(|action| {
+                mcts.searches
+                    .iter()
+                    .map(|search| {
+                        let root = &search.nodes[search.root];
+                        root.children
+                            .iter()
+                            .map(|&child| &search.nodes[child])
+                            .filter(
+                                |child| matches!(&child.edge, Some((_, edge)) if edge == action),
+                            )
+                            .map(|child| child.visits as u64)
+                            .sum::<u64>()
+                    })
+                    .sum::<u64>()
+            })
+            .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arena::{play_game, ArenaConfig, GameEnd};
+    use crate::players::player::StatelessPlayer;
+    use crate::players::random_player::RandomPlayer;
+    use crate::players::trivial_player::TrivialPlayer;
+
+    fn mcts_config(seed: u64) -> MctsConfig {
+        MctsConfig {
+            iterations: 100,
+            determinizations: 4,
+            seed,
+            ..MctsConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_beats_random_player() {
+        let config = GameConfig::new(2, 8, 2);
+        let mut num_won = 0;
+        for seed in 0..4 {
+            let mut players: Vec<Box<dyn Player>> = vec![
+                Box::new(MctsPlayer::new(config.clone(), mcts_config(seed))),
+                Box::new(RandomPlayer::new(seed)),
+            ];
+            // Both seats
+            for mcts_seat in 0..2 {
+                let arena_config = ArenaConfig::new(config.clone());
+                let record = play_game(&arena_config, seed, &mut players).unwrap();
+                assert_eq!(GameEnd::Complete, record.end);
+                num_won += (record.scores[mcts_seat] > record.scores[1 - mcts_seat]) as usize;
+                players.swap(0, 1);
+            }
+        }
+        assert!(num_won >= 6, "{}", num_won);
+    }
+
+    #[test]
+    fn test_time_budget() {
+        let config = GameConfig::new(2, 10, 3);
+        let state = GameState::new(&config, 1).unwrap();
+        let mut player = MctsPlayer::new(
+            config,
+            MctsConfig {
+                iterations: usize::MAX,
+                time_budget: Some(Duration::from_millis(50)),
+                ..MctsConfig::default()
+            },
+        );
+        let start = Instant::now();
+        player.choose_action(&state.public_state, state.current_hidden_state());
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_seeded_and_reuses_trees() {
+        let config = GameConfig::published(3).unwrap();
+        let mut state = GameState::new(&config, 5).unwrap();
+        let mut players: Vec<MctsPlayer> = (0..2)
+            .map(|_| MctsPlayer::new(config.clone(), mcts_config(1)))
+            .collect();
+        let mut num_reused = 0;
+        for _ in 0..12 {
+            if state.public_state.game_complete {
+                break;
+            }
+            // Seat 0 decides with two players seeded the same
+            let action = if state.public_state.current_player == 0 {
+                let actions: Vec<Action> = players
+                    .iter_mut()
+                    .map(|player| {
+                        player.choose_action(&state.public_state, state.current_hidden_state())
+                    })
+                    .collect();
+                assert_eq!(actions[0], actions[1]);
+                // Kept trees were searched before this move too
+                num_reused += players[0]
+                    .state
+                    .searches
+                    .iter()
+                    .filter(|search| search.nodes[search.root].visits > 100 / 4)
+                    .count();
+                actions[0].clone()
+            } else {
+                TrivialPlayer {}.choose_action(&state.public_state, state.current_hidden_state())
+            };
+            state.transition(&action);
+        }
+        assert!(num_reused > 0);
+    }
+}
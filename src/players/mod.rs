@@ -1,2 +1,83 @@
+pub mod dump_cards_player;
+pub mod greedy_player;
+pub mod ismcts_player;
+pub mod mcts_player;
+pub mod player;
+pub mod random_player;
+pub mod scout_averse_player;
+pub mod strategy_player;
 pub mod trivial_player;
-mod player;
\ No newline at end of file
+
+use crate::config::GameConfig;
+use dump_cards_player::DumpCardsPlayer;
+use greedy_player::GreedyPlayer;
+use ismcts_player::{IsmctsConfig, IsmctsPlayer};
+use mcts_player::{MctsConfig, MctsPlayer};
+use player::{Player, Stateless};
+use random_player::RandomPlayer;
+use scout_averse_player::ScoutAversePlayer;
+use trivial_player::TrivialPlayer;
+
+/// Names accepted by by_name
+pub const PLAYER_NAMES: [&str; 7] = [
+    "trivial",
+    "random",
+    "greedy",
+    "dump_cards",
+    "scout_averse",
+    "mcts",
+    "ismcts",
+];
+
+/// The player with the name, searching players use their default settings. game_config is
+/// the config of the games played, seed seeds the players that make random choices.
+pub fn by_name(name: &str, game_config: &GameConfig, seed: u64) -> Option<Box<dyn Player>> {
+    let player: Box<dyn Player> = match name {
+        "trivial" => Box::new(Stateless(TrivialPlayer {})),
+        "random" => Box::new(RandomPlayer::new(seed)),
+        "greedy" => Box::new(Stateless(GreedyPlayer {})),
+        "dump_cards" => Box::new(Stateless(DumpCardsPlayer {})),
+        "scout_averse" => Box::new(Stateless(ScoutAversePlayer {})),
+        "mcts" => Box::new(MctsPlayer::new(
+            game_config.clone(),
+            MctsConfig {
+                seed,
+                ..MctsConfig::default()
+            },
+        )),
+        "ismcts" => Box::new(IsmctsPlayer::new(
+            game_config.clone(),
+            IsmctsConfig {
+                seed,
+                ..IsmctsConfig::default()
+            },
+        )),
+        _ => return None,
+    };
+    Some(player)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arena::{play_game, ArenaConfig, GameEnd};
+    use crate::engine::MAX_PLAYERS;
+
+    #[test]
+    fn test_baselines_play_legally() {
+        assert!(by_name("unknown", &GameConfig::new(2, 10, 3), 0).is_none());
+        for num_players in 2..=MAX_PLAYERS {
+            let config = ArenaConfig::new(GameConfig::new(num_players, 10, 3));
+            for name in &PLAYER_NAMES[..5] {
+                let mut players: Vec<Box<dyn Player>> = (0..num_players)
+                    .map(|seat| by_name(name, &config.game, seat as u64).unwrap())
+                    .collect();
+                for seed in 0..3 {
+                    let record = play_game(&config, seed, &mut players).unwrap();
+                    assert!(record.illegal_moves.is_empty(), "{}", name);
+                    assert_eq!(GameEnd::Complete, record.end, "{}", name);
+                }
+            }
+        }
+    }
+}
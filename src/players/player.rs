@@ -1,6 +1,36 @@
-use crate::engine::{self};
+use crate::engine::{self, PlayerId};
 
+/// Decides the moves of one seat and may remember what it saw. A driver calls new_match
+/// before the rounds of a match, new_round before each round is played, choose_action when
+/// the seat is to act, observe after every accepted move of any player, including the one
+/// ending the round, and end_round once the round is scored.
 pub trait Player {
+    fn new_match(&mut self, _num_players: usize) {}
+
+    /// public_state is the state before the first move
+    fn new_round(&mut self, _seat: PlayerId, _public_state: &engine::PublicState) {}
+
+    fn choose_action(
+        &mut self,
+        public_state: &engine::PublicState,
+        hidden_state: &engine::PlayerHiddenState,
+    ) -> engine::Action;
+
+    fn observe(
+        &mut self,
+        _action: &engine::Action,
+        _result: &engine::TransitionResult,
+        _actor: PlayerId,
+    ) {
+    }
+
+    /// scores are those of GameComplete, indexed by PlayerId
+    fn end_round(&mut self, _scores: &[i8]) {}
+}
+
+/// A player that decides from the position alone, needing no memory. Use it as a Player
+/// through Stateless.
+pub trait StatelessPlayer {
     fn choose_action(
         &self,
         public_state: &engine::PublicState,
@@ -8,6 +38,71 @@ pub trait Player {
     ) -> engine::Action;
 }
 
-// add tests
+/// Plays a StatelessPlayer as a Player, ignoring the hooks
+pub struct Stateless<P>(pub P);
+
+impl<P: StatelessPlayer> Player for Stateless<P> {
+    fn choose_action(
+        &mut self,
+        public_state: &engine::PublicState,
+        hidden_state: &engine::PlayerHiddenState,
+    ) -> engine::Action {
+        self.0.choose_action(public_state, hidden_state)
+    }
+}
+
+impl<P: Player + ?Sized> Player for Box<P> {
+    fn new_match(&mut self, num_players: usize) {
+        (**self).new_match(num_players)
+    }
+
+    fn new_round(&mut self, seat: PlayerId, public_state: &engine::PublicState) {
+        (**self).new_round(seat, public_state)
+    }
+
+    fn choose_action(
+        &mut self,
+        public_state: &engine::PublicState,
+        hidden_state: &engine::PlayerHiddenState,
+    ) -> engine::Action {
+        (**self).choose_action(public_state, hidden_state)
+    }
+
+    fn observe(
+        &mut self,
+        action: &engine::Action,
+        result: &engine::TransitionResult,
+        actor: PlayerId,
+    ) {
+        (**self).observe(action, result, actor)
+    }
+
+    fn end_round(&mut self, scores: &[i8]) {
+        (**self).end_round(scores)
+    }
+}
+
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+    use crate::config::GameConfig;
+    use crate::engine::GameState;
+    use crate::players::trivial_player::TrivialPlayer;
+
+    #[test]
+    fn test_stateless_adapter() {
+        let mut state = GameState::new(&GameConfig::new(2, 10, 3), 123).unwrap();
+        let mut player: Box<dyn Player> = Box::new(Stateless(TrivialPlayer {}));
+        player.new_match(2);
+        player.new_round(0, &state.public_state);
+        while !state.public_state.game_complete {
+            let action = player.choose_action(&state.public_state, state.current_hidden_state());
+            assert_eq!(
+                TrivialPlayer {}.choose_action(&state.public_state, state.current_hidden_state()),
+                action
+            );
+            let result = state.transition(&action);
+            player.observe(&action, &result, 0);
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use rand::seq::IteratorRandom;
+use rand::SeedableRng;
+use rand_xoshiro::SplitMix64;
+
+use crate::engine::{self};
+use crate::players::player::Player;
+use crate::search::MoveIter;
+
+/// Picks uniformly among the legal moves
+pub struct RandomPlayer {
+    rng: SplitMix64,
+}
+
+impl RandomPlayer {
+    pub fn new(seed: u64) -> Self {
+        RandomPlayer {
+            rng: SplitMix64::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Player for RandomPlayer {
+    fn choose_action(
+        &mut self,
+        public_state: &engine::PublicState,
+        hidden_state: &engine::PlayerHiddenState,
+    ) -> engine::Action {
+        MoveIter::new(public_state, hidden_state)
+            .choose(&mut self.rng)
+            .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::GameConfig;
+
+    #[test]
+    fn test_seeded() {
+        let state = engine::GameState::new(&GameConfig::new(2, 10, 3), 1).unwrap();
+        let choices = |seed| {
+            let mut player = RandomPlayer::new(seed);
+            (0..20)
+                .map(|_| player.choose_action(&state.public_state, state.current_hidden_state()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(choices(4), choices(4));
+        assert_ne!(choices(4), choices(5));
+    }
+}
@@ -0,0 +1,64 @@
+use crate::engine::{self, build_card_set, Action, FlipHand};
+use crate::players::player::StatelessPlayer;
+use crate::search::MoveIter;
+
+/// Plays the weakest set that beats the board, saving the strong ones, and only scouts when
+/// it cannot play. Scouts with a token before using scout and show. Keeps the hand as dealt.
+pub struct ScoutAversePlayer {}
+
+impl StatelessPlayer for ScoutAversePlayer {
+    fn choose_action(
+        &self,
+        public_state: &engine::PublicState,
+        hidden_state: &engine::PlayerHiddenState,
+    ) -> engine::Action {
+        if !public_state.orientation_chosen {
+            return Action::ChooseOrientation(FlipHand::DoNotFlip);
+        }
+        let hand = &hidden_state.hand;
+        let mut moves = MoveIter::new(public_state, hidden_state);
+        // Plays come first in MoveIter order
+        let first = moves.next().unwrap();
+        let Action::PlayCards(..) = first else {
+            return first;
+        };
+        std::iter::once(first)
+            .chain(moves)
+            .take_while(|action| matches!(action, Action::PlayCards(..)))
+            .filter_map(|action| {
+                let Action::PlayCards(start_idx, end_idx) = action else {
+                    unreachable!();
+                };
+                build_card_set(&hand[start_idx as usize..end_idx as usize])
+                    .map(|card_set| (action, card_set))
+            })
+            .reduce(|weakest, play| if play.1 < weakest.1 { play } else { weakest })
+            .unwrap()
+            .0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::GameConfig;
+    use crate::engine::GameState;
+
+    #[test]
+    fn test_scouts_only_when_stuck() {
+        let mut state = GameState::new(&GameConfig::new(2, 10, 3), 1).unwrap();
+        let player = ScoutAversePlayer {};
+        let mut num_scouts = 0;
+        while !state.public_state.game_complete {
+            let action = player.choose_action(&state.public_state, state.current_hidden_state());
+            let can_play = MoveIter::new(&state.public_state, state.current_hidden_state())
+                .any(|action| matches!(action, Action::PlayCards(..)));
+            if !matches!(action, Action::PlayCards(..) | Action::ChooseOrientation(_)) {
+                assert!(!can_play);
+                num_scouts += 1;
+            }
+            state.transition(&action);
+        }
+        assert!(num_scouts > 0);
+    }
+}
@@ -0,0 +1,87 @@
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::SeedableRng;
+use rand_xoshiro::SplitMix64;
+
+use crate::cfr::Strategy;
+use crate::engine::{self, Action};
+use crate::players::player::Player;
+use crate::search::MoveIter;
+use crate::view::info_set_key;
+
+/// Plays a strategy computed by cfr, sampling each move from its probabilities
+pub struct StrategyPlayer {
+    strategy: Strategy,
+    rng: SplitMix64,
+}
+
+impl StrategyPlayer {
+    pub fn new(strategy: Strategy, seed: u64) -> Self {
+        StrategyPlayer {
+            strategy,
+            rng: SplitMix64::seed_from_u64(seed),
+        }
+    }
+
+    pub fn strategy(&self) -> &Strategy {
+        &self.strategy
+    }
+}
+
+impl Player for StrategyPlayer {
+    fn choose_action(
+        &mut self,
+        public_state: &engine::PublicState,
+        hidden_state: &engine::PlayerHiddenState,
+    ) -> engine::Action {
+        let mut actions: Vec<Action> = MoveIter::new(public_state, hidden_state).collect();
+        let key = info_set_key(public_state.current_player, public_state, hidden_state);
+        let probabilities = self.strategy.probabilities(key, actions.len());
+        let idx = WeightedIndex::new(&probabilities)
+            .unwrap()
+            .sample(&mut self.rng);
+        actions.swap_remove(idx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfr::{all_deals, CfrPlus};
+    use crate::config::GameConfig;
+    use crate::engine::{GameState, TransitionResult};
+
+    #[test]
+    fn test_plays_loaded_strategy() {
+        let deal = GameState::new(&GameConfig::new(2, 5, 0), 123).unwrap();
+        let mut cfr = CfrPlus::new(vec![deal.clone()]);
+        for _ in 0..20 {
+            cfr.iterate();
+        }
+        let mut written = vec![];
+        cfr.average_strategy().write(&mut written).unwrap();
+        let mut player = StrategyPlayer::new(Strategy::read(&written[..]).unwrap(), 1);
+
+        let mut state = deal;
+        let mut num_known = 0;
+        while !state.public_state.game_complete {
+            let public_state = &state.public_state;
+            let key = info_set_key(
+                public_state.current_player,
+                public_state,
+                state.current_hidden_state(),
+            );
+            num_known += player.strategy().get(key).is_some() as usize;
+            let action = player.choose_action(public_state, state.current_hidden_state());
+            assert!(!matches!(
+                state.transition(&action),
+                TransitionResult::IllegalMove(_)
+            ));
+        }
+        assert!(num_known > 0);
+
+        // Unknown information sets are played at random
+        let mut player = StrategyPlayer::new(Strategy::default(), 1);
+        let state = all_deals(&GameConfig::new(2, 4, 0)).swap_remove(0);
+        player.choose_action(&state.public_state, state.current_hidden_state());
+    }
+}
@@ -1,10 +1,9 @@
 use crate::engine::{self};
-use crate::players::player::Player;
+use crate::players::player::StatelessPlayer;
 use crate::search::MoveIter;
 
-
-struct TrivialPlayer {}
-impl Player for TrivialPlayer {
+pub struct TrivialPlayer {}
+impl StatelessPlayer for TrivialPlayer {
     fn choose_action(
         &self,
         public_state: &engine::PublicState,
@@ -18,32 +17,33 @@ impl Player for TrivialPlayer {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::arena::{play_game, ArenaConfig, GameEnd};
+    use crate::config::GameConfig;
+    use crate::players::player::{Player, Stateless};
+
+    fn trivial_players(num_players: usize) -> Vec<Box<dyn Player>> {
+        (0..num_players)
+            .map(|_| Box::new(Stateless(TrivialPlayer {})) as Box<dyn Player>)
+            .collect()
+    }
 
     #[test]
     fn test_choose_action() {
-        let mut state = engine::GameState::new(10, 3, 123);
-        let trivial_player_1 = TrivialPlayer {};
-        let trivial_player_2 = TrivialPlayer {};
-        while !state.public_state.game_complete {
-            let active_player: &TrivialPlayer;
-            let hidden_state: &engine::PlayerHiddenState;
-            if state.public_state.is_player_one_turn {
-                active_player = &trivial_player_1;
-                hidden_state = &state.player_one_hidden_state;
-            } else {
-                active_player = &trivial_player_2;
-                hidden_state = &state.player_two_hidden_state;
-            }
-            let action = active_player.choose_action(&state.public_state, &hidden_state);
-            let result = state.transition(&action);
-            if !matches!(result, engine::TransitionResult::IllegalMove(_)) {
-                state.display();
-            }
-            assert!(
-                !matches!(result, engine::TransitionResult::IllegalMove(_)),
-                "Illegal move: {:?}",
-                result
-            );
+        let config = ArenaConfig::new(GameConfig::new(2, 10, 3));
+        let record = play_game(&config, 123, &mut trivial_players(2)).unwrap();
+        assert_eq!(GameEnd::Complete, record.end);
+        assert!(record.illegal_moves.is_empty());
+    }
+
+    #[test]
+    fn test_choose_action_many_players() {
+        for num_players in 3..=engine::MAX_PLAYERS {
+            let config = ArenaConfig::new(GameConfig::new(num_players, 10, 3));
+            let record = play_game(&config, 123, &mut trivial_players(num_players)).unwrap();
+            assert_eq!(GameEnd::Complete, record.end);
+            assert!(record.illegal_moves.is_empty());
+            assert!(record.history.len() >= num_players);
+            assert_eq!(num_players, record.scores.len());
         }
     }
 }
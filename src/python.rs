@@ -0,0 +1,384 @@
+//! Python bindings, built with the python feature. Actions cross into Python as their index in
+//! the game's encoding::ActionSpace.
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::config::GameConfig;
+use crate::encoding::{ActionSpace, ObservationEncoder, OBSERVATION_VERSION};
+use crate::engine::{Action, GameState, PlayerId, TransitionResult};
+use crate::search::MoveIter;
+
+#[pyclass(name = "GameConfig", module = "scout_engine")]
+#[derive(Clone)]
+struct PyGameConfig {
+    config: GameConfig,
+}
+
+#[pymethods]
+impl PyGameConfig {
+    /// The original variant, see GameConfig::new
+    #[new]
+    #[pyo3(signature = (num_players=2, max_card_num=10, scout_tokens=3))]
+    fn new(num_players: usize, max_card_num: u8, scout_tokens: u8) -> PyResult<Self> {
+        let config = GameConfig::new(num_players, max_card_num, scout_tokens);
+        config
+            .validate()
+            .map_err(|err| PyValueError::new_err(err.to_string()))?;
+        Ok(PyGameConfig { config })
+    }
+
+    #[staticmethod]
+    fn published(num_players: usize) -> PyResult<Self> {
+        let config = GameConfig::published(num_players)
+            .map_err(|err| PyValueError::new_err(err.to_string()))?;
+        Ok(PyGameConfig { config })
+    }
+
+    #[getter]
+    fn num_players(&self) -> usize {
+        self.config.num_players
+    }
+
+    #[getter]
+    fn hand_size(&self) -> usize {
+        self.config.hand_size
+    }
+
+    #[getter]
+    fn action_space_size(&self) -> usize {
+        ActionSpace::new(&self.config).size()
+    }
+
+    fn observation_size(&self, history_len: usize) -> usize {
+        ObservationEncoder::new(&self.config, history_len).size()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.config)
+    }
+}
+
+#[pyclass(name = "GameState", module = "scout_engine")]
+#[derive(Clone)]
+struct PyGameState {
+    state: GameState,
+    action_space: ActionSpace,
+    encoder: ObservationEncoder,
+}
+
+impl PyGameState {
+    fn from_config(config: &GameConfig, seed: u64, history_len: usize) -> PyResult<Self> {
+        let state =
+            GameState::new(config, seed).map_err(|err| PyValueError::new_err(err.to_string()))?;
+        Ok(PyGameState {
+            state,
+            action_space: ActionSpace::new(config),
+            encoder: ObservationEncoder::new(config, history_len),
+        })
+    }
+
+    fn check_player(&self, player: PlayerId) -> PyResult<()> {
+        if player < self.state.num_players() {
+            Ok(())
+        } else {
+            Err(PyValueError::new_err(format!("No player {}", player)))
+        }
+    }
+
+    fn action(&self, action: usize) -> PyResult<Action> {
+        Action::from_index(&self.action_space, action)
+            .ok_or_else(|| PyValueError::new_err(format!("No action with index {}", action)))
+    }
+
+    /// None while the game goes on, the scores once it is complete
+    fn apply(&mut self, action: usize) -> PyResult<Option<Vec<i8>>> {
+        let action = self.action(action)?;
+        match self.state.transition(&action) {
+            TransitionResult::IllegalMove(reason) => Err(PyValueError::new_err(format!(
+                "Illegal move ({:?}): {:?}",
+                reason, action
+            ))),
+            TransitionResult::MoveAccepted => Ok(None),
+            TransitionResult::GameComplete(scores) => Ok(Some(scores)),
+        }
+    }
+}
+
+#[pymethods]
+impl PyGameState {
+    /// history_len is the number of past actions included in observations
+    #[new]
+    #[pyo3(signature = (config, seed, history_len=8))]
+    fn new(config: &PyGameConfig, seed: u64, history_len: usize) -> PyResult<Self> {
+        Self::from_config(&config.config, seed, history_len)
+    }
+
+    /// Plays the action index. Returns None while the game goes on and the score of each
+    /// player once it is complete, raises ValueError for an illegal move.
+    fn transition(&mut self, action: usize) -> PyResult<Option<Vec<i8>>> {
+        self.apply(action)
+    }
+
+    /// Indices of the legal actions, in MoveIter order
+    fn legal_actions(&self) -> Vec<usize> {
+        MoveIter::new(&self.state.public_state, self.state.current_hidden_state())
+            .map(|action| action.to_index(&self.action_space).unwrap())
+            .collect()
+    }
+
+    fn legal_action_mask(&self) -> Vec<bool> {
+        self.action_space
+            .legal_action_mask(&self.state.public_state, self.state.current_hidden_state())
+    }
+
+    /// What player can see, see encoding::ObservationEncoder for the layout
+    fn observation(&self, player: PlayerId) -> PyResult<Vec<f32>> {
+        self.check_player(player)?;
+        Ok(self.encoder.encode(
+            &self.state.public_state,
+            &self.state.hidden_states[player],
+            player,
+        ))
+    }
+
+    fn perfect_information_observation(&self, player: PlayerId) -> PyResult<Vec<f32>> {
+        self.check_player(player)?;
+        Ok(self.encoder.encode_perfect_information(&self.state, player))
+    }
+
+    fn action_to_string(&self, action: usize) -> PyResult<String> {
+        Ok(format!("{:?}", self.action(action)?))
+    }
+
+    fn calculate_hash(&self) -> u64 {
+        self.state.calculate_hash()
+    }
+
+    fn position_key(&self) -> u64 {
+        self.state.position_key()
+    }
+
+    fn clone(&self) -> Self {
+        Clone::clone(self)
+    }
+
+    fn __copy__(&self) -> Self {
+        Clone::clone(self)
+    }
+
+    fn __deepcopy__(&self, _memo: &Bound<'_, PyDict>) -> Self {
+        Clone::clone(self)
+    }
+
+    #[getter]
+    fn seed(&self) -> u64 {
+        self.state.seed
+    }
+
+    #[getter]
+    fn num_players(&self) -> usize {
+        self.state.num_players()
+    }
+
+    #[getter]
+    fn current_player(&self) -> PlayerId {
+        self.state.public_state.current_player
+    }
+
+    #[getter]
+    fn game_complete(&self) -> bool {
+        self.state.public_state.game_complete
+    }
+
+    /// The player's hand as (top, bottom) numbers
+    fn hand(&self, player: PlayerId) -> PyResult<Vec<(u8, u8)>> {
+        self.check_player(player)?;
+        Ok(self.state.hidden_states[player]
+            .hand
+            .iter()
+            .map(|card| (card.top(), card.bottom()))
+            .collect())
+    }
+
+    #[getter]
+    fn board(&self) -> Vec<(u8, u8)> {
+        self.state
+            .public_state
+            .board
+            .iter()
+            .map(|card| (card.top(), card.bottom()))
+            .collect()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "GameState(seed={}, current_player={}, game_complete={})",
+            self.state.seed,
+            self.state.public_state.current_player,
+            self.state.public_state.game_complete
+        )
+    }
+}
+
+/// Gym style environment. Observations are for the player to act, rewards are the round
+/// scores of every player once the round is complete and zero before.
+#[pyclass(name = "ScoutEnv", module = "scout_engine")]
+struct PyScoutEnv {
+    config: GameConfig,
+    history_len: usize,
+    state: Option<PyGameState>,
+}
+
+impl PyScoutEnv {
+    fn dealt_state(&self) -> PyResult<&PyGameState> {
+        self.state
+            .as_ref()
+            .ok_or_else(|| PyValueError::new_err("reset must be called first"))
+    }
+
+    fn info<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let state = self.dealt_state()?;
+        let info = PyDict::new(py);
+        info.set_item("current_player", state.current_player())?;
+        info.set_item("legal_action_mask", state.legal_action_mask())?;
+        Ok(info)
+    }
+}
+
+#[pymethods]
+impl PyScoutEnv {
+    #[new]
+    #[pyo3(signature = (config, history_len=8))]
+    fn new(config: &PyGameConfig, history_len: usize) -> Self {
+        PyScoutEnv {
+            config: config.config.clone(),
+            history_len,
+            state: None,
+        }
+    }
+
+    #[getter]
+    fn action_space_size(&self) -> usize {
+        ActionSpace::new(&self.config).size()
+    }
+
+    #[getter]
+    fn observation_size(&self) -> usize {
+        ObservationEncoder::new(&self.config, self.history_len).size()
+    }
+
+    /// The game being played, None before the first reset
+    #[getter]
+    fn state(&self) -> Option<PyGameState> {
+        self.state.clone()
+    }
+
+    /// Deals a new round, returns (observation, info)
+    fn reset<'py>(
+        &mut self,
+        py: Python<'py>,
+        seed: u64,
+    ) -> PyResult<(Vec<f32>, Bound<'py, PyDict>)> {
+        self.state = Some(PyGameState::from_config(
+            &self.config,
+            seed,
+            self.history_len,
+        )?);
+        let state = self.dealt_state()?;
+        Ok((state.observation(state.current_player())?, self.info(py)?))
+    }
+
+    /// Plays the action index for the player to act, returns
+    /// (observation, rewards, terminated, truncated, info)
+    #[allow(clippy::type_complexity)]
+    fn step<'py>(
+        &mut self,
+        py: Python<'py>,
+        action: usize,
+    ) -> PyResult<(Vec<f32>, Vec<f32>, bool, bool, Bound<'py, PyDict>)> {
+        let state = self
+            .state
+            .as_mut()
+            .ok_or_else(|| PyValueError::new_err("reset must be called first"))?;
+        let scores = state.apply(action)?;
+        let terminated = scores.is_some();
+        let rewards = match scores {
+            Some(scores) => scores.into_iter().map(|score| score as f32).collect(),
+            None => vec![0.0; self.config.num_players],
+        };
+        let state = self.dealt_state()?;
+        Ok((
+            state.observation(state.current_player())?,
+            rewards,
+            terminated,
+            false,
+            self.info(py)?,
+        ))
+    }
+}
+
+#[pymodule]
+fn scout_engine(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("OBSERVATION_VERSION", OBSERVATION_VERSION)?;
+    m.add_class::<PyGameConfig>()?;
+    m.add_class::<PyGameState>()?;
+    m.add_class::<PyScoutEnv>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pyo3::ffi::c_str;
+
+    #[test]
+    fn test_python_smoke() {
+        pyo3::append_to_inittab!(scout_engine);
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            py.run(
+                c_str!(
+                    r#"
+import copy
+import scout_engine
+
+config = scout_engine.GameConfig(2, 10, 3)
+state = scout_engine.GameState(config, 7)
+assert not state.game_complete
+assert len(state.legal_actions()) == 2
+assert len(state.observation(0)) == config.observation_size(8)
+
+copied = copy.copy(state)
+assert state.transition(state.legal_actions()[0]) is None
+assert copied.calculate_hash() != state.calculate_hash()
+try:
+    state.transition(config.action_space_size)
+    raise AssertionError("out of range action accepted")
+except ValueError:
+    pass
+
+env = scout_engine.ScoutEnv(scout_engine.GameConfig.published(4))
+observation, info = env.reset(seed=3)
+assert len(observation) == env.observation_size
+assert info["current_player"] == 0
+terminated = False
+num_steps = 0
+while not terminated:
+    mask = info["legal_action_mask"]
+    assert len(mask) == env.action_space_size
+    action = mask.index(True)
+    observation, rewards, terminated, truncated, info = env.step(action)
+    num_steps += 1
+assert len(rewards) == 4 and any(reward != 0 for reward in rewards)
+assert env.state.game_complete and num_steps > 4
+"#
+                ),
+                None,
+                None,
+            )
+            .unwrap();
+        });
+    }
+}
@@ -1,7 +1,8 @@
-use core::panic;
+use std::collections::HashSet;
 
 use crate::engine::{
-    legal_and_beats_board, Action, GameState, PlayerHiddenState, PublicState, TransitionResult,
+    apply_scout, legal_and_beats_board, Action, FlipHand, GameState, Orientation, OrientedCard,
+    PickedCard, PlayerHiddenState, PlayerId, PublicState, TransitionResult,
 };
 
 pub struct MoveIter<'a> {
@@ -12,8 +13,13 @@ pub struct MoveIter<'a> {
     num_orientations_itered: u8,
     hand_start_idx: usize,
     hand_end_idx: usize,
-    num_tokens_itered: u8,
-    scout_position_idx: usize,
+    scout_idx: usize,
+    scout_and_show_idx: usize,
+    scout_and_show_scout: Option<(PickedCard, u8, Orientation)>,
+    scout_and_show_hand: Vec<OrientedCard>,
+    scout_and_show_board: Vec<OrientedCard>,
+    scout_and_show_start_idx: usize,
+    scout_and_show_end_idx: usize,
 }
 
 impl<'a> MoveIter<'a> {
@@ -24,8 +30,110 @@ impl<'a> MoveIter<'a> {
             num_orientations_itered: 0,
             hand_start_idx: 0,
             hand_end_idx: 0,
-            num_tokens_itered: 0,
-            scout_position_idx: 0,
+            scout_idx: 0,
+            scout_and_show_idx: 0,
+            scout_and_show_scout: None,
+            scout_and_show_hand: vec![],
+            scout_and_show_board: vec![],
+            scout_and_show_start_idx: 0,
+            scout_and_show_end_idx: 0,
+        }
+    }
+
+    /// Advances (start_idx, end_idx) to the next range of hand that beats board.
+    fn next_play(
+        hand: &[OrientedCard],
+        board: &[OrientedCard],
+        start_idx: &mut usize,
+        end_idx: &mut usize,
+    ) -> Option<(u8, u8)> {
+        while *start_idx < hand.len() {
+            while *end_idx < hand.len() {
+                *end_idx += 1;
+                let proposed = &hand[*start_idx..*end_idx];
+                if legal_and_beats_board(board, proposed).is_none() {
+                    return Some((*start_idx as u8, *end_idx as u8));
+                }
+            }
+            *start_idx += 1;
+            *end_idx = *start_idx;
+        }
+        None
+    }
+
+    /// Number of distinct scouts, (picked card, insertion index, orientation). With a single
+    /// card on the board the first and last card are the same, so only FirstCard is used.
+    fn num_scouts(&self) -> usize {
+        let num_picked_cards = if self.public_state.board.len() > 1 {
+            2
+        } else {
+            1
+        };
+        num_picked_cards * (self.hidden_state.hand.len() + 1) * 2
+    }
+
+    /// The scout numbered idx, counting from 0 to num_scouts
+    fn scout(&self, idx: usize) -> (PickedCard, u8, Orientation) {
+        let num_slots = self.hidden_state.hand.len() + 1;
+        let picked_card = if idx / (num_slots * 2) == 0 {
+            PickedCard::FirstCard
+        } else {
+            PickedCard::LastCard
+        };
+        let insertion_idx = ((idx / 2) % num_slots) as u8;
+        let orientation = if idx.is_multiple_of(2) {
+            Orientation::Larger
+        } else {
+            Orientation::Smaller
+        };
+        (picked_card, insertion_idx, orientation)
+    }
+
+    /// Some Scout & Show moves end in the same state as another one, only one of them is
+    /// generated. Showing just the scouted card leaves the same hand wherever it was inserted,
+    /// so only insertion index 0 is kept. Inserting the card right after the shown set leaves
+    /// the same hand as inserting it right before, the latter is kept.
+    fn is_duplicate_scout_and_show(insertion_idx: u8, (start_idx, end_idx): (u8, u8)) -> bool {
+        let shows_only_scouted = start_idx == insertion_idx && end_idx == insertion_idx + 1;
+        (shows_only_scouted && insertion_idx != 0) || end_idx == insertion_idx
+    }
+
+    fn next_scout_and_show(&mut self) -> Option<Action> {
+        let board = &self.public_state.board;
+        let used = self.public_state.scout_and_show_used[self.public_state.current_player];
+        if used || board.is_empty() {
+            return None;
+        }
+
+        // Each scout is followed by every set in the new hand that beats what is left of the
+        // board.
+        let num_scouts = self.num_scouts();
+        loop {
+            if let Some(scout) = &self.scout_and_show_scout {
+                while let Some(play) = Self::next_play(
+                    &self.scout_and_show_hand,
+                    &self.scout_and_show_board,
+                    &mut self.scout_and_show_start_idx,
+                    &mut self.scout_and_show_end_idx,
+                ) {
+                    if !Self::is_duplicate_scout_and_show(scout.1, play) {
+                        return Some(Action::ScoutAndShow(scout.clone(), play));
+                    }
+                }
+            }
+
+            if self.scout_and_show_idx >= num_scouts {
+                return None;
+            }
+            let scout = self.scout(self.scout_and_show_idx);
+            self.scout_and_show_idx += 1;
+            let (new_hand, new_board) =
+                apply_scout(&self.hidden_state.hand, board, &scout).unwrap();
+            self.scout_and_show_scout = Some(scout);
+            self.scout_and_show_hand = new_hand;
+            self.scout_and_show_board = new_board;
+            self.scout_and_show_start_idx = 0;
+            self.scout_and_show_end_idx = 0;
         }
     }
 }
@@ -43,149 +151,859 @@ impl<'a> Iterator for MoveIter<'a> {
                 self.num_orientations_itered += 1;
                 return Some(Action::ChooseOrientation(
                     if self.num_orientations_itered == 1 {
-                        crate::engine::FlipHand::DoFlip
+                        FlipHand::DoFlip
                     } else {
-                        crate::engine::FlipHand::DoNotFlip
+                        FlipHand::DoNotFlip
                     },
                 ));
-            } else {
-                return None;
             }
+            return None;
         }
 
-        debug_assert_eq!(self.public_state.orientation_chosen, true);
+        debug_assert!(self.public_state.orientation_chosen);
 
         let hand = &self.hidden_state.hand;
-        while self.hand_start_idx < hand.len() {
-            while self.hand_end_idx < hand.len() {
-                self.hand_end_idx += 1;
-                let proposed = &hand[self.hand_start_idx..self.hand_end_idx];
-                if legal_and_beats_board(&self.public_state.board, proposed).is_none() {
-                    return Some(Action::PlayCards(
-                        self.hand_start_idx as u8,
-                        self.hand_end_idx as u8,
-                    ));
-                }
-            }
-            self.hand_start_idx += 1;
-            self.hand_end_idx = self.hand_start_idx;
+        if let Some((start_idx, end_idx)) = Self::next_play(
+            hand,
+            &self.public_state.board,
+            &mut self.hand_start_idx,
+            &mut self.hand_end_idx,
+        ) {
+            return Some(Action::PlayCards(start_idx, end_idx));
         }
 
-        let num_tokens = if self.public_state.is_player_one_turn {
-            self.public_state.player_one_scout_token_count
-        } else {
-            self.public_state.player_two_scout_token_count
-        };
-
-        if num_tokens == 0 || self.public_state.board.len() == 0 {
-            return None;
+        if self
+            .public_state
+            .can_scout(self.public_state.current_player)
+            && !self.public_state.board.is_empty()
+            && self.scout_idx < self.num_scouts()
+        {
+            let scout = self.scout(self.scout_idx);
+            self.scout_idx += 1;
+            return Some(Action::PlayScoutToken(scout));
         }
 
-        if self.num_tokens_itered == 0 {
-            while self.scout_position_idx < hand.len() {
-                let to_scout_idx = self.scout_position_idx as u8;
-                self.scout_position_idx += 1;
-                if to_scout_idx % 4 == 0 {
-                    return Some(Action::PlayScoutToken((
-                        crate::engine::PickedCard::FirstCard,
-                        to_scout_idx,
-                        crate::engine::Orientation::Larger,
-                    )));
-                } else if to_scout_idx % 4 == 1 {
-                    return Some(Action::PlayScoutToken((
-                        crate::engine::PickedCard::FirstCard,
-                        to_scout_idx,
-                        crate::engine::Orientation::Smaller,
-                    )));
-                } else if to_scout_idx % 4 == 2 && self.public_state.board.len() > 1 {
-                    return Some(Action::PlayScoutToken((
-                        crate::engine::PickedCard::LastCard,
-                        to_scout_idx,
-                        crate::engine::Orientation::Larger,
-                    )));
-                } else if to_scout_idx % 4 == 2 && self.public_state.board.len() > 1 {
-                    return Some(Action::PlayScoutToken((
-                        crate::engine::PickedCard::LastCard,
-                        to_scout_idx,
-                        crate::engine::Orientation::Smaller,
-                    )));
-                }
+        self.next_scout_and_show()
+    }
+}
+
+/// Every move from state, found by trying each action with indices up to the hand size
+/// through GameState::transition rather than by generating them. Of the moves that end in
+/// the same position only the first tried is kept. Much slower than MoveIter, used to
+/// cross-check it.
+pub fn reference_moves(state: &GameState) -> Vec<Action> {
+    let hand_len = state.current_hidden_state().hand.len() as u8;
+    let plays: Vec<(u8, u8)> = (0..=hand_len + 1)
+        .flat_map(|start_idx| (0..=hand_len + 1).map(move |end_idx| (start_idx, end_idx)))
+        .collect();
+    let mut scouts = vec![];
+    for picked_card in [PickedCard::FirstCard, PickedCard::LastCard] {
+        for insertion_idx in 0..=hand_len + 1 {
+            for orientation in [Orientation::Larger, Orientation::Smaller] {
+                scouts.push((picked_card.clone(), insertion_idx, orientation));
             }
         }
+    }
 
-        None
+    let mut candidates = vec![
+        Action::ChooseOrientation(FlipHand::DoFlip),
+        Action::ChooseOrientation(FlipHand::DoNotFlip),
+    ];
+    candidates.extend(
+        plays
+            .iter()
+            .map(|&(start_idx, end_idx)| Action::PlayCards(start_idx, end_idx)),
+    );
+    candidates.extend(scouts.iter().cloned().map(Action::PlayScoutToken));
+    for scout in &scouts {
+        candidates.extend(
+            plays
+                .iter()
+                .map(|&play| Action::ScoutAndShow(scout.clone(), play)),
+        );
     }
+
+    let mut state = state.clone();
+    let mut reached = HashSet::new();
+    candidates
+        .into_iter()
+        .filter(|action| match state.transition_with_undo(action) {
+            (_, Some(undo)) => {
+                let is_new = reached.insert(state.position_key());
+                state.undo(undo);
+                is_new
+            }
+            (_, None) => false,
+        })
+        .collect()
 }
 
-pub fn walk_games<F>(state: GameState, walker: &mut F)
+/// Calls walker on every terminal state reachable from state. The state is modified during
+/// the walk and restored before returning.
+pub fn walk_games<F>(state: &mut GameState, walker: &mut F)
 where
-    F: FnMut(GameState),
+    F: FnMut(&GameState),
 {
-    let seed = state.seed;
     if state.public_state.game_complete {
         walker(state);
         return;
     }
 
-    let hidden_state = if state.public_state.is_player_one_turn {
-        &state.player_one_hidden_state
-    } else {
-        &state.player_two_hidden_state
-    };
+    let actions: Vec<Action> =
+        MoveIter::new(&state.public_state, state.current_hidden_state()).collect();
+    for action in actions {
+        match state.transition_with_undo(&action) {
+            (TransitionResult::IllegalMove(reason), _) => {
+                panic!(
+                    "Illegal move ({:?}) (seed:{:?}): {:?}",
+                    reason, state.seed, action
+                );
+            }
+            (_, undo) => {
+                walk_games(state, walker);
+                state.undo(undo.unwrap());
+            }
+        }
+    }
+}
 
-    let mut move_iter = MoveIter::new(&state.public_state, hidden_state);
+/// Number of leaves of the game tree cut off at depth moves from state. Terminal states
+/// reached earlier count as leaves. The state is restored before returning.
+pub fn perft(state: &mut GameState, depth: usize) -> u64 {
+    if depth == 0 || state.public_state.game_complete {
+        return 1;
+    }
+    divide(state, depth).iter().map(|(_, count)| count).sum()
+}
 
-    while let Some(action) = move_iter.next() {
-        let mut new_state = state.clone();
-        match new_state.transition(&action) {
-            TransitionResult::IllegalMove(reason) => {
+/// perft split by the move made from state, in MoveIter order
+pub fn divide(state: &mut GameState, depth: usize) -> Vec<(Action, u64)> {
+    if depth == 0 || state.public_state.game_complete {
+        return vec![];
+    }
+    let actions: Vec<Action> =
+        MoveIter::new(&state.public_state, state.current_hidden_state()).collect();
+    actions
+        .into_iter()
+        .map(|action| {
+            let (result, undo) = state.transition_with_undo(&action);
+            let Some(undo) = undo else {
                 panic!(
                     "Illegal move ({:?}) (seed:{:?}): {:?}",
-                    reason, seed, action
+                    result, state.seed, action
                 );
-            }
+            };
+            let count = perft(state, depth - 1);
+            state.undo(undo);
+            (action, count)
+        })
+        .collect()
+}
+
+/// Fixed size table of values keyed on GameState::position_key. Each key has a single slot
+/// and a newer entry replaces whatever was there.
+pub struct TranspositionTable<T> {
+    entries: Vec<Option<(u64, T)>>,
+}
+
+impl<T: Clone> TranspositionTable<T> {
+    /// A table using at most memory_bytes, the number of entries is rounded down to a power
+    /// of two and is at least one
+    pub fn new(memory_bytes: usize) -> Self {
+        let entry_size = std::mem::size_of::<Option<(u64, T)>>();
+        let max_entries = (memory_bytes / entry_size).max(1);
+        let num_entries = 1 << max_entries.ilog2();
+        TranspositionTable {
+            entries: vec![None; num_entries],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn slot(&self, key: u64) -> usize {
+        (key as usize) & (self.entries.len() - 1)
+    }
+
+    pub fn get(&self, key: u64) -> Option<&T> {
+        match &self.entries[self.slot(key)] {
+            Some((entry_key, value)) if *entry_key == key => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn insert(&mut self, key: u64, value: T) {
+        let slot = self.slot(key);
+        self.entries[slot] = Some((key, value));
+    }
+}
+
+/// Size of the game tree below a position
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SubtreeCount {
+    /// Terminal states reached, as walk_games would count them
+    pub games: u64,
+    /// Every node of the tree, the position itself and terminal states included
+    pub nodes: u64,
+}
+
+/// Result of count_games
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GameCounts {
+    /// Counted as if every move order was walked
+    pub tree: SubtreeCount,
+    /// Nodes actually visited, a table hit does not walk the subtree again
+    pub visited_nodes: u64,
+    pub table_hits: u64,
+    /// Distinct terminal positions, by position key
+    pub terminal_positions: u64,
+}
+
+/// Counts the games reachable from state like walk_games, but positions reached through
+/// different move orders are only walked once while they stay in the table. The state is
+/// restored before returning.
+pub fn count_games(
+    state: &mut GameState,
+    table: &mut TranspositionTable<SubtreeCount>,
+) -> GameCounts {
+    let mut counts = GameCounts::default();
+    let mut terminal_positions = HashSet::new();
+    counts.tree = count_subtree(state, table, &mut counts, &mut terminal_positions);
+    counts.terminal_positions = terminal_positions.len() as u64;
+    counts
+}
+
+fn count_subtree(
+    state: &mut GameState,
+    table: &mut TranspositionTable<SubtreeCount>,
+    counts: &mut GameCounts,
+    terminal_positions: &mut HashSet<u64>,
+) -> SubtreeCount {
+    counts.visited_nodes += 1;
+    let key = state.position_key();
+    if state.public_state.game_complete {
+        terminal_positions.insert(key);
+        return SubtreeCount { games: 1, nodes: 1 };
+    }
+    if let Some(subtree) = table.get(key) {
+        counts.table_hits += 1;
+        return *subtree;
+    }
+
+    let mut subtree = SubtreeCount { games: 0, nodes: 1 };
+    let actions: Vec<Action> =
+        MoveIter::new(&state.public_state, state.current_hidden_state()).collect();
+    for action in actions {
+        let (result, undo) = state.transition_with_undo(&action);
+        let Some(undo) = undo else {
+            panic!(
+                "Illegal move ({:?}) (seed:{:?}): {:?}",
+                result, state.seed, action
+            );
+        };
+        let child = count_subtree(state, table, counts, terminal_positions);
+        subtree.games += child.games;
+        subtree.nodes += child.nodes;
+        state.undo(undo);
+    }
+    table.insert(key, subtree);
+    subtree
+}
+
+/// Exact value of a two player position, see solve
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Solution {
+    /// Final score of the player to move minus the score of the other player, with both
+    /// playing perfectly
+    pub value: i32,
+    /// A line of perfect play from the position to the end of the game
+    pub principal_variation: Vec<Action>,
+    /// Nodes searched, the principal variation included
+    pub nodes: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ValueBound {
+    Exact,
+    /// The value is at least the stored one
+    Lower,
+    /// The value is at most the stored one
+    Upper,
+}
+
+/// What solve keeps per position in its transposition table
+#[derive(Debug, Clone)]
+pub struct SolverEntry {
+    /// For the player to move
+    value: i32,
+    bound: ValueBound,
+    best_move: Option<Action>,
+}
+
+/// Solves a two player game with every hand visible: alpha-beta search to the end of the
+/// game, with the table's best move and then longer plays searched first. The table can be
+/// reused between positions of the same round. The state is restored before returning.
+pub fn solve(state: &mut GameState, table: &mut TranspositionTable<SolverEntry>) -> Solution {
+    assert_eq!(2, state.num_players(), "solve needs a two player game");
+    let mut nodes = 0;
+    let value = solve_node(state, table, i32::MIN + 1, i32::MAX, &mut nodes);
+
+    // Follow the best moves, searching again where the table lost the exact value
+    let mut principal_variation = vec![];
+    let mut undos = vec![];
+    while !state.public_state.game_complete {
+        let best_move = match table.get(state.position_key()) {
+            Some(SolverEntry {
+                bound: ValueBound::Exact,
+                best_move: Some(best_move),
+                ..
+            }) => best_move.clone(),
             _ => {
-                walk_games(new_state, walker);
+                solve_node(state, table, i32::MIN + 1, i32::MAX, &mut nodes);
+                continue;
+            }
+        };
+        let (_, undo) = state.transition_with_undo(&best_move);
+        undos.push(undo.unwrap());
+        principal_variation.push(best_move);
+    }
+    for undo in undos.into_iter().rev() {
+        state.undo(undo);
+    }
+
+    Solution {
+        value,
+        principal_variation,
+        nodes,
+    }
+}
+
+/// Score difference for the player to move at the end of the game
+fn final_value(scores: &[i8], player: PlayerId) -> i32 {
+    scores[player] as i32 - scores[1 - player] as i32
+}
+
+/// Searched first, the lower the better
+fn move_order(action: &Action) -> i32 {
+    match action {
+        Action::PlayCards(start_idx, end_idx) => -(*end_idx as i32 - *start_idx as i32) * 2,
+        Action::ScoutAndShow(_, (start_idx, end_idx)) => {
+            -(*end_idx as i32 - *start_idx as i32) * 2 + 1
+        }
+        Action::PlayScoutToken(_) => 1,
+        Action::ChooseOrientation(_) => 2,
+    }
+}
+
+/// Fail-soft alpha-beta, the value is for the player to move
+fn solve_node(
+    state: &mut GameState,
+    table: &mut TranspositionTable<SolverEntry>,
+    mut alpha: i32,
+    beta: i32,
+    nodes: &mut u64,
+) -> i32 {
+    *nodes += 1;
+    let player = state.public_state.current_player;
+    if state.public_state.game_complete {
+        let scores = state.final_scores().unwrap_or_else(|| {
+            panic!("Game complete without final scores (seed:{:?})", state.seed)
+        });
+        return final_value(scores, player);
+    }
+
+    let key = state.position_key();
+    let mut table_move = None;
+    if let Some(entry) = table.get(key) {
+        match entry.bound {
+            ValueBound::Exact => return entry.value,
+            ValueBound::Lower if entry.value >= beta => return entry.value,
+            ValueBound::Upper if entry.value <= alpha => return entry.value,
+            _ => {}
+        }
+        table_move = entry.best_move.clone();
+    }
+
+    let mut actions: Vec<Action> =
+        MoveIter::new(&state.public_state, state.current_hidden_state()).collect();
+    actions.sort_by_key(|action| (Some(action) != table_move.as_ref(), move_order(action)));
+
+    let original_alpha = alpha;
+    let mut best_value = i32::MIN;
+    let mut best_move = None;
+    for action in actions {
+        let (result, undo) = state.transition_with_undo(&action);
+        let Some(undo) = undo else {
+            panic!(
+                "Illegal move ({:?}) (seed:{:?}): {:?}",
+                result, state.seed, action
+            );
+        };
+        let value = match result {
+            TransitionResult::GameComplete(scores) => {
+                *nodes += 1;
+                final_value(&scores, player)
+            }
+            // Scouting can leave the same player to move
+            _ if state.public_state.current_player == player => {
+                solve_node(state, table, alpha, beta, nodes)
             }
+            _ => -solve_node(state, table, -beta, -alpha, nodes),
+        };
+        state.undo(undo);
+
+        if value > best_value {
+            best_value = value;
+            best_move = Some(action);
+        }
+        alpha = alpha.max(value);
+        if alpha >= beta {
+            break;
         }
     }
+
+    let bound = if best_value <= original_alpha {
+        ValueBound::Upper
+    } else if best_value >= beta {
+        ValueBound::Lower
+    } else {
+        ValueBound::Exact
+    };
+    table.insert(
+        key,
+        SolverEntry {
+            value: best_value,
+            bound,
+            best_move,
+        },
+    );
+    best_value
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::GameConfig;
+    use crate::engine::{Rules, ScoutTokenRule};
+    use std::collections::HashMap;
     #[test]
     fn test_iter_orientations() {
-        let state = GameState::new(4, 0, 123);
-        let move_iter = MoveIter::new(&state.public_state, &state.player_one_hidden_state);
+        let state = GameState::new(&GameConfig::new(2, 4, 0), 123).unwrap();
+        let move_iter = MoveIter::new(&state.public_state, &state.hidden_states[0]);
         assert_eq!(move_iter.count(), 2);
     }
 
+    #[test]
+    fn test_move_iter_matches_reference() {
+        use crate::engine::RoundEndRule;
+        use rand::seq::IteratorRandom;
+        use rand::SeedableRng;
+        use rand_xoshiro::SplitMix64;
+
+        let configs = [
+            GameConfig::new(2, 8, 2),
+            GameConfig::new(3, 8, 1),
+            GameConfig {
+                rules: Rules {
+                    scout_tokens: ScoutTokenRule::AwardOwner,
+                    round_end: RoundEndRule::Stuck,
+                },
+                ..GameConfig::new(2, 8, 0)
+            },
+            GameConfig {
+                rules: Rules::published(),
+                ..GameConfig::new(4, 8, 0)
+            },
+        ];
+        let mut rng = SplitMix64::seed_from_u64(11);
+        let mut num_scout_and_shows = 0;
+        for config in &configs {
+            for seed in 0..3 {
+                let mut state = GameState::new(config, seed).unwrap();
+                while !state.public_state.game_complete {
+                    let moves: Vec<Action> =
+                        MoveIter::new(&state.public_state, state.current_hidden_state()).collect();
+                    let mut reached = HashSet::new();
+                    for action in &moves {
+                        let mut next_state = state.clone();
+                        let result = next_state.transition(action);
+                        assert!(!matches!(result, TransitionResult::IllegalMove(_)));
+                        // No two moves end in the same position
+                        assert!(reached.insert(next_state.position_key()), "{:?}", action);
+                    }
+
+                    let reference = reference_moves(&state);
+                    assert_eq!(reference.len(), moves.len());
+                    for action in &reference {
+                        let mut next_state = state.clone();
+                        next_state.transition(action);
+                        assert!(reached.contains(&next_state.position_key()), "{:?}", action);
+                    }
+
+                    num_scout_and_shows += moves
+                        .iter()
+                        .filter(|action| matches!(action, Action::ScoutAndShow(..)))
+                        .count();
+                    state.transition(moves.iter().choose(&mut rng).unwrap());
+                }
+            }
+        }
+        assert!(num_scout_and_shows > 0);
+    }
+
+    #[test]
+    fn test_scout_moves() {
+        let mut state = GameState::new(&GameConfig::new(2, 10, 3), 2).unwrap();
+        state.transition(&Action::ChooseOrientation(FlipHand::DoNotFlip));
+        state.transition(&Action::ChooseOrientation(FlipHand::DoNotFlip));
+        state.transition(&Action::PlayCards(0, 1));
+        let scouts = |state: &GameState| {
+            MoveIter::new(&state.public_state, state.current_hidden_state())
+                .filter(|action| matches!(action, Action::PlayScoutToken(_)))
+                .collect::<Vec<_>>()
+        };
+        // A single card on the board, every insertion index including after the last card
+        let hand_len = state.current_hidden_state().hand.len();
+        let single = scouts(&state);
+        assert_eq!((hand_len + 1) * 2, single.len());
+        assert!(single.contains(&Action::PlayScoutToken((
+            PickedCard::FirstCard,
+            hand_len as u8,
+            Orientation::Smaller
+        ))));
+        assert!(!single
+            .iter()
+            .any(|action| matches!(action, Action::PlayScoutToken((PickedCard::LastCard, ..)))));
+
+        // Two cards on the board, both ends in both orientations
+        state.public_state.board = state.hidden_states[0].hand[..2].to_vec();
+        let double = scouts(&state);
+        assert_eq!((hand_len + 1) * 4, double.len());
+        assert!(double.contains(&Action::PlayScoutToken((
+            PickedCard::LastCard,
+            0,
+            Orientation::Smaller
+        ))));
+    }
+
     #[test]
     fn test_walker_small() {
-        let state = GameState::new(4, 0, 123);
+        let mut state = GameState::new(&GameConfig::new(2, 4, 0), 123).unwrap();
         let mut count = 0;
-        let mut count_fn = |state: GameState| {
+        let mut count_fn = |state: &GameState| {
             if state.public_state.game_complete {
                 count += 1;
             }
         };
-        walk_games(state, &mut count_fn);
+        walk_games(&mut state, &mut count_fn);
         // total games depends on the orientation each player picks. there are no choices after that.
         // so 4 games total.
         assert_eq!(count, 4);
     }
 
+    #[test]
+    fn test_walker_three_players() {
+        let mut state = GameState::new(&GameConfig::new(3, 5, 0), 123).unwrap();
+        let mut count = 0;
+        let mut count_fn = |state: &GameState| {
+            if let TransitionResult::GameComplete(scores) =
+                &state.public_state.action_history.last().unwrap().2
+            {
+                assert_eq!(3, scores.len());
+            }
+            count += 1;
+        };
+        walk_games(&mut state, &mut count_fn);
+        // one card each, so only the orientation each player picks matters
+        assert_eq!(count, 8);
+    }
+
+    #[test]
+    fn test_walker_scout_and_show() {
+        let mut state = GameState::new(&GameConfig::new(2, 5, 0), 123).unwrap();
+        let mut count = 0;
+        let mut count_fn = |_: &GameState| {
+            count += 1;
+        };
+        let before = state.clone();
+        walk_games(&mut state, &mut count_fn);
+        assert_eq!(count, 1114);
+        // The walk leaves the state as it found it
+        assert_eq!(before, state);
+
+        // Without the chip and without scout tokens, only plays from hand remain
+        state.public_state.scout_and_show_used = vec![true; 2];
+        state.refresh_position_key();
+        let mut count = 0;
+        let mut count_fn = |_: &GameState| {
+            count += 1;
+        };
+        walk_games(&mut state, &mut count_fn);
+        assert_eq!(count, 11);
+    }
+
+    #[test]
+    fn test_count_games() {
+        let mut state = GameState::new(&GameConfig::new(2, 5, 0), 123).unwrap();
+        let mut terminal_positions = HashSet::new();
+        walk_games(&mut state, &mut |state: &GameState| {
+            terminal_positions.insert(state.position_key());
+        });
+
+        let before = state.clone();
+        let mut table = TranspositionTable::new(1 << 20);
+        let counts = count_games(&mut state, &mut table);
+        assert_eq!(before, state);
+        assert_eq!(1114, counts.tree.games);
+        assert_eq!(terminal_positions.len() as u64, counts.terminal_positions);
+        assert!(counts.terminal_positions < counts.tree.games);
+        assert!(counts.table_hits > 0);
+        assert!(counts.visited_nodes < counts.tree.nodes);
+
+        // A single entry table still counts the same tree, with more of it walked
+        let mut tiny_table = TranspositionTable::new(0);
+        assert_eq!(1, tiny_table.len());
+        let tiny_counts = count_games(&mut state, &mut tiny_table);
+        assert_eq!(counts.tree, tiny_counts.tree);
+        assert_eq!(counts.terminal_positions, tiny_counts.terminal_positions);
+        assert!(tiny_counts.visited_nodes > counts.visited_nodes);
+    }
+
+    /// perft with the moves of reference_moves
+    fn reference_perft(state: &mut GameState, depth: usize) -> u64 {
+        if depth == 0 || state.public_state.game_complete {
+            return 1;
+        }
+        reference_moves(state)
+            .iter()
+            .map(|action| {
+                let (_, undo) = state.transition_with_undo(action);
+                let count = reference_perft(state, depth - 1);
+                state.undo(undo.unwrap());
+                count
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_perft_fixtures() {
+        // <num players> <max card num> <scout tokens> <seed> <depth> <leaves>
+        let fixtures = include_str!("../fixtures/perft.txt");
+        let mut num_checked = 0;
+        for line in fixtures.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<u64> = line
+                .split_whitespace()
+                .map(|field| field.parse().unwrap())
+                .collect();
+            let [num_players, max_card_num, scout_tokens, seed, depth, leaves] = fields[..] else {
+                panic!("Bad fixture line: {}", line);
+            };
+            let config =
+                GameConfig::new(num_players as usize, max_card_num as u8, scout_tokens as u8);
+            let mut state = GameState::new(&config, seed).unwrap();
+            assert_eq!(leaves, perft(&mut state, depth as usize), "{}", line);
+            // The counts were made with MoveIter, so they must also hold for moves found
+            // without it
+            assert_eq!(
+                leaves,
+                reference_perft(&mut state, depth as usize),
+                "{}",
+                line
+            );
+            num_checked += 1;
+        }
+        assert!(num_checked > 0);
+    }
+
+    #[test]
+    fn test_divide() {
+        let mut state = GameState::new(&GameConfig::new(2, 5, 0), 123).unwrap();
+        let before = state.clone();
+        let divided = divide(&mut state, 3);
+        assert_eq!(before, state);
+        assert_eq!(2, divided.len());
+        assert_eq!(
+            perft(&mut state, 3),
+            divided.iter().map(|(_, count)| count).sum::<u64>()
+        );
+        assert_eq!(1, perft(&mut state, 0));
+        // Deep enough to reach the end of every game
+        assert_eq!(1114, perft(&mut state, 100));
+    }
+
+    #[test]
+    fn test_transposition_table() {
+        let mut table = TranspositionTable::new(100 * 24);
+        assert_eq!(64, table.len());
+        table.insert(3, 30u64);
+        assert_eq!(Some(&30), table.get(3));
+        assert_eq!(None, table.get(4));
+        // 67 shares the slot of 3 and replaces it
+        table.insert(67, 670);
+        assert_eq!(None, table.get(3));
+        assert_eq!(Some(&670), table.get(67));
+    }
+
+    #[test]
+    fn test_walker_award_owner() {
+        let config = GameConfig {
+            rules: Rules {
+                scout_tokens: ScoutTokenRule::AwardOwner,
+                ..Default::default()
+            },
+            ..GameConfig::new(2, 5, 0)
+        };
+        let mut state = GameState::new(&config, 123).unwrap();
+        // Unlimited scouting, compare to 1114 games when scouting is limited to S&S. Too many
+        // games to walk one by one.
+        let counts = count_games(&mut state, &mut TranspositionTable::new(1 << 20));
+        assert_eq!(counts.tree.games, 2745756);
+    }
+
+    #[test]
+    fn test_walker_published() {
+        let mut state = GameState::new(
+            &GameConfig {
+                rules: Rules::published(),
+                ..GameConfig::new(2, 5, 0)
+            },
+            123,
+        )
+        .unwrap();
+        // Walk from after the first play, the whole tree is too large
+        for action in [
+            Action::ChooseOrientation(FlipHand::DoNotFlip),
+            Action::ChooseOrientation(FlipHand::DoNotFlip),
+            Action::PlayCards(0, 1),
+        ] {
+            assert_eq!(TransitionResult::MoveAccepted, state.transition(&action));
+        }
+        let mut count = 0;
+        let mut returned_count = 0;
+        let mut count_fn = |state: &GameState| {
+            let public_state = &state.public_state;
+            // A round ending on the owner's turn never counts the owner's hand
+            if public_state.board_owner == Some(public_state.current_player) {
+                returned_count += 1;
+                let owner = public_state.current_player;
+                let (_, _, result) = public_state.action_history.last().unwrap();
+                assert_eq!(
+                    &TransitionResult::GameComplete(
+                        (0..2)
+                            .map(|p| {
+                                let in_hand = if p == owner {
+                                    0
+                                } else {
+                                    public_state.card_counts[p] as i8
+                                };
+                                (public_state.won_cards[p] + public_state.scout_token_counts[p])
+                                    as i8
+                                    - in_hand
+                            })
+                            .collect()
+                    ),
+                    result
+                );
+            }
+            count += 1;
+        };
+        walk_games(&mut state, &mut count_fn);
+        assert_eq!(count, 67343);
+        assert_eq!(returned_count, 3440);
+    }
+
     #[test]
     fn test_walker_medium() {
-        let state = GameState::new(6, 1, 123);
+        let mut state = GameState::new(&GameConfig::new(2, 6, 1), 123).unwrap();
+        // Scout & Show multiplies the tree size, so it is marked used for both players. The
+        // count covers playing and scouting with tokens only, not the real move tree.
+        state.public_state.scout_and_show_used = vec![true; 2];
+        state.refresh_position_key();
         let mut count = 0;
-        let mut count_fn = |state: GameState| {
+        let mut count_fn = |_: &GameState| {
             count += 1;
         };
-        walk_games(state, &mut count_fn);
-        // total games depends on the orientation each player picks. there are no choices after that.
-        // so 4 games total.
-        assert_eq!(count, 4040);
+        walk_games(&mut state, &mut count_fn);
+        assert_eq!(count, 46986);
+    }
+
+    /// Minimax without pruning, each position computed once
+    fn minimax(state: &mut GameState, values: &mut HashMap<u64, i32>) -> i32 {
+        let key = state.position_key();
+        if let Some(value) = values.get(&key) {
+            return *value;
+        }
+        let player = state.public_state.current_player;
+        let actions: Vec<Action> =
+            MoveIter::new(&state.public_state, state.current_hidden_state()).collect();
+        let value = actions
+            .iter()
+            .map(|action| {
+                let (result, undo) = state.transition_with_undo(action);
+                let value = match result {
+                    TransitionResult::GameComplete(scores) => final_value(&scores, player),
+                    _ if state.public_state.current_player == player => minimax(state, values),
+                    _ => -minimax(state, values),
+                };
+                state.undo(undo.unwrap());
+                value
+            })
+            .max()
+            .unwrap();
+        values.insert(key, value);
+        value
+    }
+
+    #[test]
+    fn test_solve() {
+        for (config, seed) in [
+            (GameConfig::new(2, 5, 0), 123),
+            (GameConfig::new(2, 6, 0), 5),
+            (GameConfig::new(2, 5, 1), 123),
+        ] {
+            let mut state = GameState::new(&config, seed).unwrap();
+            let before = state.clone();
+            let mut table = TranspositionTable::new(1 << 20);
+            let solution = solve(&mut state, &mut table);
+            assert_eq!(before, state);
+            let mut values = HashMap::new();
+            assert_eq!(minimax(&mut state, &mut values), solution.value);
+            assert!(solution.nodes < values.len() as u64);
+
+            // A single entry table gets the same value, with more searching
+            let mut tiny_table = TranspositionTable::new(0);
+            let tiny_solution = solve(&mut state, &mut tiny_table);
+            assert_eq!(solution.value, tiny_solution.value);
+            assert!(tiny_solution.nodes > solution.nodes);
+
+            // Both lines reach the value, and every position along them has the value
+            for principal_variation in [
+                solution.principal_variation,
+                tiny_solution.principal_variation,
+            ] {
+                let mut line = state.clone();
+                let mut result = TransitionResult::MoveAccepted;
+                for action in &principal_variation {
+                    let player = line.public_state.current_player;
+                    let value = solve(&mut line, &mut table).value;
+                    let value_for_first = if player == 0 { value } else { -value };
+                    assert_eq!(solution.value, value_for_first);
+                    result = line.transition(action);
+                }
+                let TransitionResult::GameComplete(scores) = result else {
+                    panic!("Principal variation does not end the game: {:?}", result);
+                };
+                assert_eq!(solution.value, final_value(&scores, 0));
+            }
+        }
     }
 }
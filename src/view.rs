@@ -0,0 +1,250 @@
+use std::hash::{Hash, Hasher};
+
+use crate::engine::{GameState, OrientedCard, PlayerHiddenState, PlayerId, PublicState};
+use crate::search::MoveIter;
+
+/// What one seat knows of a game: the public state, including the cards every player saw
+/// being scouted into a hand, and the seat's own hand. Every game state the seat cannot tell
+/// apart from the real one has the same view, so the view stands for the seat's information
+/// set.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PlayerView {
+    player: PlayerId,
+    public_state: PublicState,
+    hidden_state: PlayerHiddenState,
+}
+
+impl PlayerView {
+    pub fn new(state: &GameState, player: PlayerId) -> Self {
+        PlayerView {
+            player,
+            public_state: state.public_state.clone(),
+            hidden_state: state.hidden_states[player].clone(),
+        }
+    }
+
+    /// The view of the player to move, from the states a Player is given
+    pub fn from_states(public_state: &PublicState, hidden_state: &PlayerHiddenState) -> Self {
+        PlayerView {
+            player: public_state.current_player,
+            public_state: public_state.clone(),
+            hidden_state: hidden_state.clone(),
+        }
+    }
+
+    pub fn player(&self) -> PlayerId {
+        self.player
+    }
+
+    pub fn public_state(&self) -> &PublicState {
+        &self.public_state
+    }
+
+    pub fn hidden_state(&self) -> &PlayerHiddenState {
+        &self.hidden_state
+    }
+
+    /// The legal moves when it is the seat's turn
+    pub fn moves(&self) -> MoveIter<'_> {
+        debug_assert_eq!(self.player, self.public_state.current_player);
+        MoveIter::new(&self.public_state, &self.hidden_state)
+    }
+
+    /// A slot per card in the player's hand, None for the cards the seat has not seen
+    pub fn known_hand(&self, player: PlayerId) -> Vec<Option<OrientedCard>> {
+        if player == self.player {
+            self.hidden_state.hand.iter().copied().map(Some).collect()
+        } else {
+            self.public_state.known_cards[player].clone()
+        }
+    }
+
+    /// Same for every game state in the information set. It covers the action history, so
+    /// different paths to the same position are different information sets.
+    pub fn info_set_key(&self) -> u64 {
+        info_set_key(self.player, &self.public_state, &self.hidden_state)
+    }
+
+    /// As GameState::display, with the cards the seat has not seen as ??
+    pub fn display(&self) {
+        let public_state = &self.public_state;
+        println!("## Info Set Key: {:?}", self.info_set_key());
+        if public_state.game_complete {
+            println!("--Game Complete--");
+        } else {
+            println!("--Turn: player {}--", public_state.current_player);
+        }
+
+        for player in 0..public_state.num_players() {
+            print!(
+                "Player {}{}: [Tokens {:?}] [Won {:?}] [S&S Used {:?}] [Hand:",
+                player,
+                if player == self.player { " (you)" } else { "" },
+                public_state.scout_token_counts[player],
+                public_state.won_cards[player],
+                public_state.scout_and_show_used[player]
+            );
+            for card in self.known_hand(player) {
+                match card {
+                    Some(card) => print!(" {}", card),
+                    None => print!(" ??"),
+                }
+            }
+            println!("]");
+        }
+
+        print!("Board:");
+        for card in &public_state.board {
+            print!(" {}", card);
+        }
+        println!();
+    }
+}
+
+/// 64 bit FNV-1a. Unlike DefaultHasher the hash function itself is fixed. Integers are
+/// hashed as little endian bytes, usize and isize widened to 64 bits.
+struct StableHasher(u64);
+
+impl StableHasher {
+    fn new() -> Self {
+        StableHasher(0xcbf29ce484222325)
+    }
+}
+
+impl Hasher for StableHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ byte as u64).wrapping_mul(0x100000001b3);
+        }
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, i: u128) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// PlayerView::info_set_key without building the view. The states are fed through their
+/// derived Hash impls, whose byte stream Rust does not guarantee between compiler versions,
+/// nor does it survive changes to the hashed types. Keys can change with either, saved
+/// strategies are then rejected by cfr::Strategy::read.
+pub fn info_set_key(
+    player: PlayerId,
+    public_state: &PublicState,
+    hidden_state: &PlayerHiddenState,
+) -> u64 {
+    let mut hasher = StableHasher::new();
+    player.hash(&mut hasher);
+    public_state.hash(&mut hasher);
+    hidden_state.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::GameConfig;
+    use crate::engine::{Action, FlipHand, Orientation, PickedCard};
+    use rand::seq::IteratorRandom;
+    use rand::SeedableRng;
+    use rand_xoshiro::SplitMix64;
+
+    #[test]
+    fn test_known_cards_match_hands() {
+        let mut rng = SplitMix64::seed_from_u64(3);
+        let mut num_known = 0;
+        for config in [GameConfig::new(2, 10, 3), GameConfig::published(4).unwrap()] {
+            for seed in 0..10 {
+                let mut state = GameState::new(&config, seed).unwrap();
+                while !state.public_state.game_complete {
+                    let action = MoveIter::new(&state.public_state, state.current_hidden_state())
+                        .choose(&mut rng)
+                        .unwrap();
+                    state.transition(&action);
+
+                    let view = PlayerView::new(&state, 0);
+                    for player in 0..state.num_players() {
+                        let hand = &state.hidden_states[player].hand;
+                        let known_hand = view.known_hand(player);
+                        assert_eq!(hand.len(), known_hand.len());
+                        for (card, known_card) in hand.iter().zip(known_hand) {
+                            if let Some(known_card) = known_card {
+                                assert_eq!(*card, known_card);
+                                num_known += (player != 0) as usize;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        assert!(num_known > 0);
+    }
+
+    #[test]
+    fn test_view_hides_other_hands() {
+        let config = GameConfig::new(3, 10, 3);
+        let mut state = GameState::new(&config, 4).unwrap();
+        for _ in 0..3 {
+            state.transition(&Action::ChooseOrientation(FlipHand::DoNotFlip));
+        }
+        state.transition(&Action::PlayCards(0, 1));
+        // Player 1 scouts the card player 0 showed, the others now know where it is
+        let shown = state.public_state.board[0];
+        state.transition(&Action::PlayScoutToken((
+            PickedCard::FirstCard,
+            2,
+            Orientation::Larger,
+        )));
+        let scouted = state.hidden_states[1].hand[2];
+        assert_eq!(shown.top().max(shown.bottom()), scouted.top());
+        let view = PlayerView::new(&state, 2);
+        let known_hand = view.known_hand(1);
+        assert_eq!(Some(scouted), known_hand[2]);
+        assert_eq!(1, known_hand.iter().flatten().count());
+        assert_eq!(0, view.known_hand(0).iter().flatten().count());
+        assert!(view.known_hand(2).iter().all(|card| card.is_some()));
+
+        // Exchanging cards player 2 has not seen between players 0 and 1 is not visible to
+        // player 2
+        let mut swapped = state.clone();
+        let card = swapped.hidden_states[0].hand[0];
+        swapped.hidden_states[0].hand[0] = swapped.hidden_states[1].hand[0];
+        swapped.hidden_states[1].hand[0] = card;
+        assert_ne!(state.calculate_hash(), swapped.calculate_hash());
+        assert_eq!(view, PlayerView::new(&swapped, 2));
+        assert_eq!(
+            view.info_set_key(),
+            PlayerView::new(&swapped, 2).info_set_key()
+        );
+
+        // But it is to the players who hold them
+        assert_ne!(
+            PlayerView::new(&state, 0).info_set_key(),
+            PlayerView::new(&swapped, 0).info_set_key()
+        );
+        assert_ne!(
+            view.info_set_key(),
+            PlayerView::new(&state, 1).info_set_key()
+        );
+        view.display();
+    }
+}